@@ -0,0 +1,57 @@
+//! FEN parsing and formatting throughput, run with `cargo bench --bench fen`.
+
+use std::{hint::black_box, time::Instant};
+
+use ataxxgen::{rng::Rng, Board};
+
+const POSITIONS: usize = 10_000;
+const ROUNDS: usize = 20;
+
+/// Times `f` over every position, `ROUNDS` times, and prints positions per second.
+fn bench<T>(name: &str, items: &[T], mut f: impl FnMut(&T)) {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for item in items {
+            f(item);
+        }
+    }
+    let elapsed = start.elapsed();
+    let rate = (items.len() * ROUNDS) as f64 / elapsed.as_secs_f64();
+    println!("{name:<20} {:>8.2} M/s", rate / 1e6);
+}
+
+fn main() {
+    let mut rng = Rng::new(0x5EED);
+    let mut boards = Vec::with_capacity(POSITIONS);
+    let mut board = Board::default();
+    while boards.len() < POSITIONS {
+        if board.game_over() {
+            board = Board::default();
+        }
+        board.make_random_move(|lo, hi| rng.range(lo, hi));
+        boards.push(board);
+    }
+    let fens: Vec<String> = boards.iter().map(Board::fen).collect();
+
+    bench("parse", &fens, |fen| {
+        black_box(fen.parse::<Board>().unwrap());
+    });
+    bench("fen", &boards, |board| {
+        black_box(board.fen());
+    });
+    let mut out = String::new();
+    bench("write_fen", &boards, |board| {
+        out.clear();
+        board.write_fen(&mut out).unwrap();
+        black_box(&out);
+    });
+    bench("fen_buf", &boards, |board| {
+        black_box(board.fen_buf());
+    });
+    let mut out = Vec::new();
+    bench("write_fen_io", &boards, |board| {
+        out.clear();
+        board.write_fen_io(&mut out).unwrap();
+        black_box(&out);
+    });
+}
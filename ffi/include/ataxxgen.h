@@ -0,0 +1,69 @@
+/*
+ * C interface to the ataxxgen move generator.
+ *
+ * Boards are opaque handles owned by the caller: every board returned by
+ * ataxx_board_new, ataxx_board_from_fen or ataxx_board_clone must be released
+ * with ataxx_board_free. Moves are 16-bit packed moves, as written by
+ * ataxx_board_legal_moves, and can be turned into text with ataxx_move_to_str.
+ *
+ * Functions that write text behave like snprintf: they write at most len
+ * bytes including the terminating NUL and return the full length of the text.
+ *
+ * The static and shared libraries, libataxxgen.a and libataxxgen.so, are built
+ * by `cargo build --release -p ataxxgen-ffi`.
+ */
+#ifndef ATAXXGEN_H
+#define ATAXXGEN_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct ataxx_board ataxx_board;
+
+#define ATAXX_OK 0
+#define ATAXX_ERROR (-1)
+
+/* Results of ataxx_board_outcome. */
+#define ATAXX_ONGOING 0
+#define ATAXX_WHITE_WINS 1
+#define ATAXX_BLACK_WINS 2
+#define ATAXX_DRAW 3
+
+/* A board in the start position. */
+ataxx_board *ataxx_board_new(void);
+/* A board from a FEN such as "x5o/7/7/7/7/7/o5x x 0 1", or NULL if it is invalid. */
+ataxx_board *ataxx_board_from_fen(const char *fen);
+/* A copy of board, or NULL if board is NULL. */
+ataxx_board *ataxx_board_clone(const ataxx_board *board);
+/* Accepts NULL. */
+void ataxx_board_free(ataxx_board *board);
+
+size_t ataxx_board_to_fen(const ataxx_board *board, char *buf, size_t len);
+
+/* Writes up to capacity legal moves and returns how many there are, which may be more. */
+size_t ataxx_board_legal_moves(const ataxx_board *board, uint16_t *moves, size_t capacity);
+
+/* Play a move given as text ("b2", "a1c3", "0000") or packed. Illegal moves leave the
+ * board unchanged and return ATAXX_ERROR. */
+int ataxx_board_make_move_str(ataxx_board *board, const char *move);
+int ataxx_board_make_move(ataxx_board *board, uint16_t move);
+
+size_t ataxx_move_to_str(uint16_t move, char *buf, size_t len);
+
+/* Nonzero once the game is over. */
+int ataxx_board_game_over(const ataxx_board *board);
+/* One of the ATAXX_ONGOING, ATAXX_WHITE_WINS, ATAXX_BLACK_WINS and ATAXX_DRAW results. */
+int ataxx_board_outcome(const ataxx_board *board);
+
+/* The number of lines of depth moves from the board. */
+uint64_t ataxx_perft(const ataxx_board *board, uint8_t depth);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,192 @@
+//! The static and dynamic C libraries of `ataxxgen`, exporting the functions declared in
+//! `include/ataxxgen.h`. They live here rather than in the main crate so that Rust users of
+//! it do not link the exported symbols.
+
+use std::{
+    ffi::{c_char, c_int, CStr},
+    ptr, slice,
+};
+
+use rules::{perft::perft, Board, Move, PackedMove, Player};
+
+// Boards are handed out as owned pointers to `Board`, which C sees as the opaque
+// `ataxx_board`, and moves as the raw `PackedMove`.
+
+pub const ATAXX_OK: c_int = 0;
+pub const ATAXX_ERROR: c_int = -1;
+
+pub const ATAXX_ONGOING: c_int = 0;
+pub const ATAXX_WHITE_WINS: c_int = 1;
+pub const ATAXX_BLACK_WINS: c_int = 2;
+pub const ATAXX_DRAW: c_int = 3;
+
+/// Copies `text` into the `len` bytes at `buf` with a terminating NUL, cutting it short if
+/// needed, and returns its full length like `snprintf`.
+///
+/// # Safety
+///
+/// `buf` must be valid for writes of `len` bytes, or `len` must be zero.
+unsafe fn write_c_string(text: &str, buf: *mut c_char, len: usize) -> usize {
+    if len > 0 && !buf.is_null() {
+        let copied = text.len().min(len - 1);
+        ptr::copy_nonoverlapping(text.as_ptr().cast(), buf, copied);
+        *buf.add(copied) = 0;
+    }
+    text.len()
+}
+
+/// A new board in the start position, to be freed with `ataxx_board_free`.
+#[no_mangle]
+pub extern "C" fn ataxx_board_new() -> *mut Board {
+    Box::into_raw(Box::new(Board::new()))
+}
+
+/// A new board from a FEN, or null if it is not valid.
+///
+/// # Safety
+///
+/// `fen` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ataxx_board_from_fen(fen: *const c_char) -> *mut Board {
+    if fen.is_null() {
+        return ptr::null_mut();
+    }
+    let Ok(fen) = CStr::from_ptr(fen).to_str() else {
+        return ptr::null_mut();
+    };
+    match fen.parse::<Board>() {
+        Ok(board) => Box::into_raw(Box::new(board)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// A new board in the same position as `board`, or null if `board` is null.
+///
+/// # Safety
+///
+/// `board` must be null or a board returned by this library and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn ataxx_board_clone(board: *const Board) -> *mut Board {
+    match board.as_ref() {
+        Some(board) => Box::into_raw(Box::new(*board)),
+        None => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+///
+/// `board` must be null or a board returned by this library and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn ataxx_board_free(board: *mut Board) {
+    if !board.is_null() {
+        drop(Box::from_raw(board));
+    }
+}
+
+/// Writes the FEN of `board` to `buf` like `snprintf`, returning its length without the NUL.
+///
+/// # Safety
+///
+/// `board` must be a live board, and `buf` valid for writes of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ataxx_board_to_fen(board: *const Board, buf: *mut c_char, len: usize) -> usize {
+    write_c_string(&(*board).fen_buf(), buf, len)
+}
+
+/// Writes up to `capacity` legal moves of `board` to `moves`, in move generation order,
+/// and returns the number of legal moves, which may be more.
+///
+/// # Safety
+///
+/// `board` must be a live board, and `moves` valid for writes of `capacity` moves.
+#[no_mangle]
+pub unsafe extern "C" fn ataxx_board_legal_moves(board: *const Board, moves: *mut u16, capacity: usize) -> usize {
+    let out: &mut [u16] = if moves.is_null() { &mut [] } else { slice::from_raw_parts_mut(moves, capacity) };
+    let mut count = 0;
+    (*board).generate_moves(|mv| {
+        if let Some(slot) = out.get_mut(count) {
+            *slot = PackedMove::new(mv).raw();
+        }
+        count += 1;
+        false
+    });
+    count
+}
+
+fn make_legal_move(board: &mut Board, mv: Option<Move>) -> c_int {
+    match mv {
+        Some(mv) if board.is_legal(mv) => {
+            board.make_move(mv);
+            ATAXX_OK
+        }
+        _ => ATAXX_ERROR,
+    }
+}
+
+/// Plays a move given like `b2`, `a1c3` or `0000`. Returns `ATAXX_ERROR`, leaving the board
+/// unchanged, if the move is not legal.
+///
+/// # Safety
+///
+/// `board` must be a live board, and `mv` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ataxx_board_make_move_str(board: *mut Board, mv: *const c_char) -> c_int {
+    if mv.is_null() {
+        return ATAXX_ERROR;
+    }
+    let mv = CStr::from_ptr(mv).to_str().ok().and_then(|mv| mv.parse().ok());
+    make_legal_move(&mut *board, mv)
+}
+
+/// Plays a packed move, as written by `ataxx_board_legal_moves`. Returns `ATAXX_ERROR`,
+/// leaving the board unchanged, if the move is not legal.
+///
+/// # Safety
+///
+/// `board` must be a live board.
+#[no_mangle]
+pub unsafe extern "C" fn ataxx_board_make_move(board: *mut Board, mv: u16) -> c_int {
+    make_legal_move(&mut *board, PackedMove::from_raw(mv).unpack())
+}
+
+/// Writes a packed move as text to `buf` like `snprintf`, returning its length without the
+/// NUL.
+///
+/// # Safety
+///
+/// `buf` must be valid for writes of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ataxx_move_to_str(mv: u16, buf: *mut c_char, len: usize) -> usize {
+    write_c_string(&PackedMove::from_raw(mv).to_string(), buf, len)
+}
+
+/// # Safety
+///
+/// `board` must be a live board.
+#[no_mangle]
+pub unsafe extern "C" fn ataxx_board_game_over(board: *const Board) -> c_int {
+    c_int::from((*board).game_over())
+}
+
+/// One of `ATAXX_ONGOING`, `ATAXX_WHITE_WINS`, `ATAXX_BLACK_WINS` or `ATAXX_DRAW`.
+///
+/// # Safety
+///
+/// `board` must be a live board.
+#[no_mangle]
+pub unsafe extern "C" fn ataxx_board_outcome(board: *const Board) -> c_int {
+    match (*board).outcome() {
+        None => ATAXX_ONGOING,
+        Some(Some(Player::White)) => ATAXX_WHITE_WINS,
+        Some(Some(Player::Black)) => ATAXX_BLACK_WINS,
+        Some(None) => ATAXX_DRAW,
+    }
+}
+
+/// # Safety
+///
+/// `board` must be a live board.
+#[no_mangle]
+pub unsafe extern "C" fn ataxx_perft(board: *const Board, depth: u8) -> u64 {
+    perft(&*board, depth)
+}
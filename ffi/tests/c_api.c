@@ -0,0 +1,75 @@
+/* Exercises the C interface; run by tests/c_api.rs. */
+#include <stdio.h>
+#include <string.h>
+
+#include "ataxxgen.h"
+
+static int failures = 0;
+
+#define CHECK(cond)                                                  \
+    do {                                                             \
+        if (!(cond)) {                                               \
+            fprintf(stderr, "%s:%d: failed: %s\n", __FILE__, __LINE__, #cond); \
+            failures++;                                              \
+        }                                                            \
+    } while (0)
+
+int main(void) {
+    char fen[128];
+    char text[8];
+    uint16_t moves[256];
+
+    ataxx_board *board = ataxx_board_new();
+    CHECK(ataxx_board_to_fen(board, fen, sizeof fen) == strlen("x5o/7/7/7/7/7/o5x x 0 1"));
+    CHECK(strcmp(fen, "x5o/7/7/7/7/7/o5x x 0 1") == 0);
+    /* short buffers are cut and still terminated. */
+    CHECK(ataxx_board_to_fen(board, text, sizeof text) == strlen(fen));
+    CHECK(strcmp(text, "x5o/7/7") == 0);
+
+    size_t count = ataxx_board_legal_moves(board, moves, 256);
+    CHECK(count == 16);
+    CHECK(ataxx_board_legal_moves(board, moves, 4) == 16);
+    CHECK(ataxx_board_legal_moves(board, NULL, 0) == 16);
+    CHECK(ataxx_perft(board, 1) == 16);
+    CHECK(ataxx_perft(board, 3) == 6460);
+
+    ataxx_board *copy = ataxx_board_clone(board);
+    CHECK(ataxx_board_make_move_str(board, "g2") == ATAXX_OK);
+    CHECK(ataxx_board_make_move_str(board, "d4") == ATAXX_ERROR);
+    CHECK(ataxx_board_make_move_str(board, "nonsense") == ATAXX_ERROR);
+    ataxx_board_to_fen(board, fen, sizeof fen);
+    CHECK(strcmp(fen, "x5o/7/7/7/7/6x/o5x o 0 1") == 0);
+
+    CHECK(ataxx_move_to_str(moves[0], text, sizeof text) == strlen(text));
+    CHECK(ataxx_board_make_move(copy, moves[0]) == ATAXX_OK);
+    CHECK(ataxx_board_make_move(copy, moves[0]) == ATAXX_ERROR);
+    CHECK(ataxx_board_game_over(copy) == 0);
+    CHECK(ataxx_board_outcome(copy) == ATAXX_ONGOING);
+    ataxx_board_free(copy);
+    ataxx_board_free(board);
+
+    CHECK(ataxx_board_from_fen("x5o/7/7 x 0 1") == NULL);
+    CHECK(ataxx_board_from_fen("x5o/7/7/7/7/7/o5x x 0 0") == NULL);
+    CHECK(ataxx_board_from_fen("x5o/7/7/7/7/7/o5x x 0 4294967295") == NULL);
+    board = ataxx_board_from_fen("xxxxxxx/xxxxxxx/xxxxxxx/xxxxxxx/ooooooo/ooooooo/ooooooo o 0 1");
+    CHECK(board != NULL);
+    CHECK(ataxx_board_game_over(board));
+    CHECK(ataxx_board_outcome(board) == ATAXX_WHITE_WINS);
+    CHECK(ataxx_board_legal_moves(board, moves, 256) == 0);
+    ataxx_board_free(board);
+    ataxx_board_free(NULL);
+    CHECK(ataxx_board_clone(NULL) == NULL);
+
+    /* moves from the last move number a board can hold do not overflow it. */
+    CHECK(ataxx_board_from_fen("x5o/7/7/7/7/7/o5x o 0 32768") == NULL);
+    board = ataxx_board_from_fen("x5o/7/7/7/7/7/o5x o 0 32767");
+    CHECK(board != NULL);
+    CHECK(ataxx_board_make_move_str(board, "b2") == ATAXX_OK);
+    CHECK(ataxx_board_make_move_str(board, "b6") == ATAXX_OK);
+    ataxx_board_free(board);
+
+    if (failures == 0) {
+        printf("ok\n");
+    }
+    return failures == 0 ? 0 : 1;
+}
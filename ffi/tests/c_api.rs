@@ -0,0 +1,38 @@
+use std::{path::Path, process::Command};
+
+/// Builds the C test program against the static library and runs it.
+#[test]
+fn c_program_uses_the_library() {
+    // tests only get the Rust library, so build the static one into the same profile
+    // directory, the parent of the `deps` directory this test runs from.
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let exe = std::env::current_exe().unwrap();
+    let profile_dir = exe.parent().and_then(Path::parent).unwrap();
+    let mut cargo = Command::new(env!("CARGO"));
+    cargo.args(["build", "--lib", "--quiet", "--manifest-path"]).arg(root.join("Cargo.toml"));
+    cargo.arg("--target-dir").arg(profile_dir.parent().unwrap());
+    if profile_dir.ends_with("release") {
+        cargo.arg("--release");
+    }
+    let output = cargo.output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let library = profile_dir.join("libataxxgen.a");
+
+    let program = std::env::temp_dir().join(format!("ataxxgen-ffi-{}", std::process::id()));
+    let output = Command::new("cc")
+        .arg(root.join("tests/c_api.c"))
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg("-o")
+        .arg(&program)
+        .arg(&library)
+        .args(["-lpthread", "-ldl", "-lm"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = Command::new(&program).output().unwrap();
+    std::fs::remove_file(&program).unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
@@ -0,0 +1,496 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    process::exit,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    time::{Duration, Instant},
+};
+
+use ataxxgen::{
+    pgn::{self, Eval},
+    openings::load_openings,
+    stats::{Pentanomial, Sprt, SprtDecision, Wdl},
+    uai::{EngineError, EngineProcess, GoParams, Score},
+    Board, Move, Player,
+};
+
+const USAGE: &str = "\
+usage: arena --engine <command> --engine <command> [options]
+
+Plays UAI engines against each other in a round robin. Each round plays one opening
+twice between every pair of engines, with colours reversed, and every move is checked
+against the rules before it is played.
+
+options:
+  --engine <command>    an engine to play, as a program followed by its arguments,
+                        separated by spaces; give at least two
+  --openings <path>     FEN or EPD file of openings, used in order [the start position]
+  --rounds <n>          number of rounds [1]
+  --concurrency <n>     games played at once [1]
+  --tc <base+inc>       time control in seconds, such as 10+0.1 [10+0.1]
+  --nodes <n>           also limit every search to n nodes
+  --margin <ms>         time an engine may overrun its clock by [100]
+  --hash <mb>           hash size to set in every engine
+  --pgn <path>          file to write the games to
+  --sprt <elo0,elo1[,alpha,beta]>
+                        stop a match between two engines as soon as a sequential
+                        probability ratio test of the first against the second decides;
+                        alpha and beta default to 0.05
+  --event <name>        PGN event name [arena]";
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct EngineSpec {
+    program: String,
+    args: Vec<String>,
+    name: String,
+}
+
+struct Options {
+    engines: Vec<EngineSpec>,
+    openings: Option<PathBuf>,
+    rounds: usize,
+    concurrency: usize,
+    base: Duration,
+    inc: Duration,
+    nodes: Option<u64>,
+    margin: Duration,
+    hash: Option<usize>,
+    pgn: Option<PathBuf>,
+    sprt: Option<Sprt>,
+    event: String,
+}
+
+fn parse_seconds(s: &str) -> Option<Duration> {
+    s.parse::<f64>().ok().filter(|s| s.is_finite() && *s >= 0.0).map(Duration::from_secs_f64)
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        engines: Vec::new(),
+        openings: None,
+        rounds: 1,
+        concurrency: 1,
+        base: Duration::from_secs(10),
+        inc: Duration::from_millis(100),
+        nodes: None,
+        margin: Duration::from_millis(100),
+        hash: None,
+        pgn: None,
+        sprt: None,
+        event: "arena".into(),
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            exit(0);
+        }
+        let value = args.next().ok_or_else(|| format!("missing value for {arg}"))?;
+        let invalid = || format!("invalid value for {arg}: {value}");
+        match arg.as_str() {
+            "--engine" => {
+                let mut words = value.split_whitespace().map(str::to_owned);
+                let program = words.next().ok_or_else(invalid)?;
+                options.engines.push(EngineSpec { name: program.clone(), program, args: words.collect() });
+            }
+            "--openings" => options.openings = Some(PathBuf::from(&value)),
+            "--rounds" => options.rounds = value.parse().map_err(|_| invalid())?,
+            "--concurrency" => options.concurrency = value.parse().map_err(|_| invalid())?,
+            "--tc" => {
+                let (base, inc) = value.split_once('+').unwrap_or((&value, "0"));
+                options.base = parse_seconds(base).ok_or_else(invalid)?;
+                options.inc = parse_seconds(inc).ok_or_else(invalid)?;
+            }
+            "--nodes" => options.nodes = Some(value.parse().map_err(|_| invalid())?),
+            "--margin" => options.margin = Duration::from_millis(value.parse().map_err(|_| invalid())?),
+            "--hash" => options.hash = Some(value.parse().map_err(|_| invalid())?),
+            "--pgn" => options.pgn = Some(PathBuf::from(&value)),
+            "--sprt" => options.sprt = Some(value.parse().map_err(|e| format!("{e}: {value}"))?),
+            "--event" => options.event = value.clone(),
+            _ => return Err(format!("unknown option: {arg}")),
+        }
+    }
+
+    if options.engines.len() < 2 {
+        return Err("need at least two engines".into());
+    }
+    if options.sprt.is_some() && options.engines.len() != 2 {
+        return Err("an SPRT needs exactly two engines".into());
+    }
+    if options.concurrency == 0 {
+        return Err("need a concurrency of at least one".into());
+    }
+    Ok(options)
+}
+
+/// Starts an engine and gets it ready for its first game.
+fn start_engine(spec: &EngineSpec, options: &Options) -> Result<EngineProcess, EngineError> {
+    let mut engine = EngineProcess::spawn(&spec.program, &spec.args)?;
+    engine.init(HANDSHAKE_TIMEOUT)?;
+    if let Some(hash) = options.hash {
+        engine.set_option("Hash", &hash.to_string())?;
+    }
+    engine.is_ready(HANDSHAKE_TIMEOUT)?;
+    Ok(engine)
+}
+
+struct Job {
+    /// The index of the game. Games 2k and 2k + 1 are a pair with colours reversed.
+    game: usize,
+    round: usize,
+    white: usize,
+    black: usize,
+    opening: Board,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Termination {
+    /// The game ended by the rules.
+    Normal,
+    TimeForfeit,
+    IllegalMove,
+    /// The engine crashed or stopped answering.
+    Abandoned,
+}
+
+/// A move as played in a game, with the engine's evaluation of it if it gave one.
+struct PlayedMove {
+    mv: Move,
+    score: Option<Score>,
+    depth: Option<u32>,
+}
+
+struct Game {
+    game: usize,
+    round: usize,
+    white: usize,
+    black: usize,
+    opening: Board,
+    moves: Vec<PlayedMove>,
+    winner: Option<Player>,
+    termination: Termination,
+    /// What went wrong, if the game did not end normally.
+    reason: Option<String>,
+}
+
+/// Plays one game to the end, adjudicating it as lost for an engine that makes an illegal
+/// move, runs out of time or stops working.
+fn play_game(job: &Job, white: &mut EngineProcess, black: &mut EngineProcess, options: &Options) -> Game {
+    let mut game = Game {
+        game: job.game,
+        round: job.round,
+        white: job.white,
+        black: job.black,
+        opening: job.opening,
+        moves: Vec::new(),
+        winner: None,
+        termination: Termination::Normal,
+        reason: None,
+    };
+    let mut board = job.opening;
+    let mut played = Vec::new();
+    let mut clocks = [options.base; 2];
+
+    while !board.game_over() {
+        let side = board.turn();
+        let params = GoParams {
+            wtime: Some(clocks[0]),
+            btime: Some(clocks[1]),
+            winc: Some(options.inc),
+            binc: Some(options.inc),
+            nodes: options.nodes,
+            ..GoParams::default()
+        };
+        let (engine, clock) = match side {
+            Player::White => (&mut *white, &mut clocks[0]),
+            Player::Black => (&mut *black, &mut clocks[1]),
+        };
+
+        let start = Instant::now();
+        let answer = engine.go(&job.opening, &played, &params, *clock + options.margin);
+        let elapsed = start.elapsed();
+        let loser = |game: &mut Game, termination, reason: String| {
+            game.winner = Some(match side {
+                Player::White => Player::Black,
+                Player::Black => Player::White,
+            });
+            game.termination = termination;
+            game.reason = Some(format!("{side:?} {reason}"));
+        };
+
+        let best = match answer {
+            Ok(best) if elapsed <= *clock + options.margin => best,
+            Ok(_) | Err(EngineError::Timeout) => {
+                loser(&mut game, Termination::TimeForfeit, "loses on time".into());
+                return game;
+            }
+            Err(e) => {
+                loser(&mut game, Termination::Abandoned, format!("crashed: {e}"));
+                return game;
+            }
+        };
+        *clock = clock.saturating_sub(elapsed) + options.inc;
+
+        let mv = best.mv.parse::<Move>().ok().filter(|&mv| board.is_legal(mv));
+        let Some(mv) = mv else {
+            loser(&mut game, Termination::IllegalMove, format!("makes an illegal move: {}", best.mv));
+            return game;
+        };
+
+        board.make_move(mv);
+        played.push(mv);
+        game.moves.push(PlayedMove { mv, score: best.score, depth: best.depth });
+    }
+
+    game.winner = board.outcome().flatten();
+    game
+}
+
+fn result_str(winner: Option<Player>) -> &'static str {
+    match winner {
+        Some(Player::White) => "1-0",
+        Some(Player::Black) => "0-1",
+        None => "1/2-1/2",
+    }
+}
+
+/// The PGN record of a played game, with the engines' evaluations in the move comments and
+/// what went wrong, if anything, after the last move.
+fn to_pgn(game: &Game, options: &Options, date: &str) -> pgn::Game {
+    let mut record = pgn::Game::new(game.opening);
+    record.set_tag("Event", options.event.as_str());
+    record.set_tag("Site", "?");
+    record.set_tag("Date", date);
+    record.set_tag("Round", (game.round + 1).to_string());
+    record.set_tag("White", options.engines[game.white].name.as_str());
+    record.set_tag("Black", options.engines[game.black].name.as_str());
+    record.set_tag("PlyCount", game.moves.len().to_string());
+    record.set_tag("TimeControl", format!("{}+{}", options.base.as_secs_f64(), options.inc.as_secs_f64()));
+    let termination = match game.termination {
+        Termination::Normal => "normal",
+        Termination::TimeForfeit => "time forfeit",
+        Termination::IllegalMove => "rules infraction",
+        Termination::Abandoned => "abandoned",
+    };
+    record.set_tag("Termination", termination);
+
+    for played in &game.moves {
+        let id = record.push(played.mv).expect("moves are checked before they are played");
+        record.tree.node_mut(id).eval = played.score.map(|score| Eval { score, depth: played.depth });
+    }
+    let last = record.tree.mainline_end(record.tree.root());
+    record.tree.node_mut(last).comment = game.reason.clone();
+    record.result = Some(game.winner);
+    record
+}
+
+/// Plays jobs until there are none left, keeping one process of each engine running and
+/// restarting any that stop working.
+fn worker(
+    jobs: &[Job],
+    next: &AtomicUsize,
+    stop: &AtomicBool,
+    options: &Options,
+    sender: mpsc::Sender<Result<Game, String>>,
+) {
+    let mut engines: Vec<Option<EngineProcess>> = options.engines.iter().map(|_| None).collect();
+    while !stop.load(Ordering::Relaxed) {
+        let index = next.fetch_add(1, Ordering::Relaxed);
+        let Some(job) = jobs.get(index) else { return };
+
+        for side in [job.white, job.black] {
+            // an engine that crashed between games is restarted rather than blamed.
+            let ready = match &mut engines[side] {
+                Some(engine) => engine.new_game(HANDSHAKE_TIMEOUT).is_ok(),
+                None => false,
+            };
+            if !ready {
+                engines[side] = None;
+                match start_engine(&options.engines[side], options) {
+                    Ok(engine) => engines[side] = Some(engine),
+                    Err(e) => {
+                        let _ = sender.send(Err(format!("could not start {}: {e}", options.engines[side].name)));
+                        return;
+                    }
+                }
+            }
+        }
+
+        let [white, black] = engines.get_disjoint_mut([job.white, job.black]).unwrap();
+        let game = play_game(job, white.as_mut().unwrap(), black.as_mut().unwrap(), options);
+        if matches!(game.termination, Termination::TimeForfeit | Termination::Abandoned) {
+            let loser = if game.winner == Some(Player::White) { job.black } else { job.white };
+            engines[loser] = None;
+        }
+        if sender.send(Ok(game)).is_err() {
+            return;
+        }
+    }
+}
+
+fn main() {
+    let mut options = parse_options().unwrap_or_else(|e| {
+        eprintln!("error: {e}\n\n{USAGE}");
+        exit(1);
+    });
+
+    // start every engine once up front, to catch bad commands early and learn their names.
+    for i in 0..options.engines.len() {
+        let engine = start_engine(&options.engines[i], &options).unwrap_or_else(|e| {
+            eprintln!("error: could not start {}: {e}", options.engines[i].program);
+            exit(1);
+        });
+        let name = engine.name().to_owned();
+        engine.quit();
+        let taken = options.engines[..i]
+            .iter()
+            .filter(|e| e.name == name || e.name.starts_with(&format!("{name} #")))
+            .count();
+        options.engines[i].name = if taken == 0 { name } else { format!("{name} #{}", taken + 1) };
+    }
+
+    let openings = match &options.openings {
+        Some(path) => load_openings(path).unwrap_or_else(|e| {
+            eprintln!("error: could not read {}: {e}", path.display());
+            exit(1);
+        }),
+        None => vec![Board::default()],
+    };
+    if openings.is_empty() {
+        eprintln!("error: no openings in the opening file");
+        exit(1);
+    }
+
+    let mut jobs = Vec::new();
+    for round in 0..options.rounds {
+        let opening = openings[round % openings.len()];
+        for first in 0..options.engines.len() {
+            for second in first + 1..options.engines.len() {
+                for (white, black) in [(first, second), (second, first)] {
+                    jobs.push(Job { game: jobs.len(), round, white, black, opening });
+                }
+            }
+        }
+    }
+
+    let mut pgn = options.pgn.as_ref().map(|path| {
+        BufWriter::new(File::create(path).unwrap_or_else(|e| {
+            eprintln!("error: could not create {}: {e}", path.display());
+            exit(1);
+        }))
+    });
+    let date = pgn::today();
+    let mut standings = vec![Wdl::default(); options.engines.len()];
+    // the first engine's results against the second, for matches between two engines.
+    let mut pentanomial = Pentanomial::default();
+    let mut first_of_pair = HashMap::new();
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::scope(|s| {
+        for _ in 0..options.concurrency.min(jobs.len()) {
+            let sender = sender.clone();
+            let (jobs, next, stop, options) = (&jobs, &next, &stop, &options);
+            s.spawn(move || worker(jobs, next, stop, options, sender));
+        }
+        drop(sender);
+
+        let mut finished = 0;
+        for game in receiver {
+            let game = game.unwrap_or_else(|e| {
+                eprintln!("error: {e}");
+                exit(1);
+            });
+            finished += 1;
+            let (white, black) = (game.white, game.black);
+            match game.winner {
+                Some(Player::White) => (standings[white].wins += 1, standings[black].losses += 1),
+                Some(Player::Black) => (standings[black].wins += 1, standings[white].losses += 1),
+                None => (standings[white].draws += 1, standings[black].draws += 1),
+            };
+            eprintln!(
+                "finished game {} of {} ({} vs {}): {}{}",
+                finished,
+                jobs.len(),
+                options.engines[white].name,
+                options.engines[black].name,
+                result_str(game.winner),
+                game.reason.as_ref().map_or(String::new(), |r| format!(" {{{r}}}")),
+            );
+            if let Some(pgn) = &mut pgn {
+                writeln!(pgn, "{}", to_pgn(&game, &options, &date)).and_then(|_| pgn.flush()).unwrap_or_else(|e| {
+                    eprintln!("error: failed to write the PGN: {e}");
+                    exit(1);
+                });
+            }
+
+            if options.engines.len() != 2 {
+                continue;
+            }
+            let score = match game.winner {
+                Some(winner) if (winner == Player::White) == (white == 0) => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            };
+            if let Some(first) = first_of_pair.remove(&(game.game / 2)) {
+                pentanomial.add_pair(first, score);
+            } else {
+                first_of_pair.insert(game.game / 2, score);
+            }
+            let wdl = standings[0];
+            eprintln!(
+                "score of {} vs {}: {} - {} - {} [{:.3}] {}",
+                options.engines[0].name,
+                options.engines[1].name,
+                wdl.wins,
+                wdl.losses,
+                wdl.draws,
+                wdl.score().unwrap_or(0.5),
+                wdl.games()
+            );
+            if let Some(elo) = pentanomial.elo() {
+                eprintln!("elo difference: {elo} (pentanomial), LOS: {:.1}%", wdl.los() * 100.0);
+            }
+            if let Some(sprt) = &options.sprt {
+                let llr = sprt.llr_pentanomial(&pentanomial);
+                let (lower, upper) = sprt.bounds();
+                eprintln!("sprt [{}, {}]: LLR {llr:.2} ({lower:.2}, {upper:.2})", sprt.elo0, sprt.elo1);
+                if let Some(decision) = sprt.decide(llr) {
+                    if !stop.swap(true, Ordering::Relaxed) {
+                        let accepted = match decision {
+                            SprtDecision::AcceptH0 => "H0",
+                            SprtDecision::AcceptH1 => "H1",
+                        };
+                        eprintln!("sprt: {accepted} accepted, finishing the games in progress");
+                    }
+                }
+            }
+        }
+    });
+
+    println!("{:<30} {:>6} {:>6} {:>6} {:>6} {:>7}", "engine", "games", "wins", "draws", "losses", "points");
+    for (spec, s) in options.engines.iter().zip(&standings) {
+        println!(
+            "{:<30} {:>6} {:>6} {:>6} {:>6} {:>7.1}",
+            spec.name,
+            s.wins + s.draws + s.losses,
+            s.wins,
+            s.draws,
+            s.losses,
+            s.wins as f64 + s.draws as f64 / 2.0
+        );
+    }
+    if options.engines.len() == 2 {
+        if let Some(elo) = standings[0].elo() {
+            println!("elo difference of {} against {}: {elo}", options.engines[0].name, options.engines[1].name);
+        }
+    }
+}
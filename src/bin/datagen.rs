@@ -0,0 +1,208 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::BufWriter,
+    process::exit,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use ataxxgen::{
+    openings::{random_opening, OPENING_ATTEMPTS},
+    record::{DatasetWriter, Format, PositionRecord},
+    rng::Rng,
+    search::{Limits, Search},
+};
+
+const USAGE: &str = "\
+usage: datagen --output <path> [options]
+
+Plays self-play games and writes every non-terminal position with its search score
+and the final result of the game.
+
+options:
+  --output <path>       file to write positions to
+  --format <fmt>        `text` (fen | score | result lines) or `binary` (position records) [text]
+  --threads <n>         number of worker threads [1]
+  --games <n>           games to play per thread [100]
+  --nodes <n>           node limit of the search at each move [5000]
+  --random-plies <n>    random moves played at the start of each game [8]
+  --tt-mb <n>           transposition table size per thread [16]
+  --seed <n>            base seed [from the clock]
+  --deterministic       clear the search state between games and write games in order,
+                        so that the output depends only on the seed and options";
+
+struct Options {
+    output: String,
+    format: Format,
+    threads: usize,
+    games: u64,
+    nodes: u64,
+    random_plies: u32,
+    tt_mb: usize,
+    seed: u64,
+    deterministic: bool,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        output: String::new(),
+        format: Format::Text,
+        threads: 1,
+        games: 100,
+        nodes: 5000,
+        random_plies: 8,
+        tt_mb: 16,
+        seed: Rng::from_entropy().next_u64(),
+        deterministic: false,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--deterministic" {
+            options.deterministic = true;
+            continue;
+        }
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            exit(0);
+        }
+        let value = args.next().ok_or_else(|| format!("missing value for {arg}"))?;
+        let invalid = |_| format!("invalid value for {arg}: {value}");
+        match arg.as_str() {
+            "--output" => options.output = value.clone(),
+            "--format" => options.format = value.parse().map_err(|e| format!("{e}: {value}"))?,
+            "--threads" => options.threads = value.parse().map_err(invalid)?,
+            "--games" => options.games = value.parse().map_err(invalid)?,
+            "--nodes" => options.nodes = value.parse().map_err(invalid)?,
+            "--random-plies" => options.random_plies = value.parse().map_err(invalid)?,
+            "--tt-mb" => options.tt_mb = value.parse().map_err(invalid)?,
+            "--seed" => options.seed = value.parse().map_err(invalid)?,
+            _ => return Err(format!("unknown option: {arg}")),
+        }
+    }
+
+    if options.output.is_empty() {
+        return Err("no output file given".into());
+    }
+    if options.threads == 0 {
+        return Err("need at least one thread".into());
+    }
+    Ok(options)
+}
+
+/// Plays one game, returning its positions labelled with their scores and the final result.
+fn play_game(search: &mut Search, rng: &mut Rng, options: &Options) -> Result<Vec<PositionRecord>, String> {
+    let Some(mut board) = random_opening(options.random_plies, rng) else {
+        return Err(format!(
+            "no game was still in play after {} random plies in {OPENING_ATTEMPTS} attempts",
+            options.random_plies
+        ));
+    };
+
+    let mut records = Vec::new();
+    while !board.game_over() {
+        let result = search.search(&board, Limits::nodes(options.nodes));
+        let Some(mv) = result.best_move else { break };
+        let score = result.score.clamp(i32::from(i16::MIN), i32::from(i16::MAX)) as i16;
+        records.push(PositionRecord { board, score: Some(score), result: None });
+        board.make_move(mv);
+    }
+
+    let outcome = board.outcome();
+    for record in &mut records {
+        record.result = outcome;
+    }
+    Ok(records)
+}
+
+fn main() {
+    let options = parse_options().unwrap_or_else(|e| {
+        eprintln!("error: {e}\n\n{USAGE}");
+        exit(1);
+    });
+
+    let file = File::create(&options.output).unwrap_or_else(|e| {
+        eprintln!("error: could not create {}: {e}", options.output);
+        exit(1);
+    });
+    let mut output = DatasetWriter::new(BufWriter::new(file), options.format);
+
+    let total_games = options.games * options.threads as u64;
+    let start = Instant::now();
+    let (sender, receiver) = mpsc::channel::<(u64, Vec<PositionRecord>)>();
+
+    std::thread::scope(|s| {
+        for thread in 0..options.threads {
+            let sender = sender.clone();
+            let options = &options;
+            s.spawn(move || {
+                let mut search = Search::new(options.tt_mb);
+                // games are interleaved between threads so that in deterministic mode the
+                // writer never has to hold back more than a few games.
+                let mut game = thread as u64;
+                while game < total_games {
+                    let mut rng = Rng::new(options.seed ^ game.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+                    if options.deterministic {
+                        search.clear();
+                    }
+                    let records = play_game(&mut search, &mut rng, options).unwrap_or_else(|e| {
+                        eprintln!("error: {e}");
+                        exit(1);
+                    });
+                    if sender.send((game, records)).is_err() {
+                        return;
+                    }
+                    game += options.threads as u64;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut games = 0;
+        let mut positions = 0;
+        let mut pending = BTreeMap::new();
+        let mut next_game = 0;
+        let mut last_report = Instant::now();
+        loop {
+            match receiver.recv_timeout(Duration::from_millis(200)) {
+                Ok((game, records)) => {
+                    games += 1;
+                    positions += records.len();
+                    pending.insert(game, records);
+                    while let Some(records) = if options.deterministic {
+                        pending.remove(&next_game)
+                    } else {
+                        pending.pop_first().map(|(_, records)| records)
+                    } {
+                        next_game += 1;
+                        for record in &records {
+                            output.write_record(record).unwrap_or_else(|e| {
+                                eprintln!("error: failed to write to {}: {e}", options.output);
+                                exit(1);
+                            });
+                        }
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+
+            if last_report.elapsed() >= Duration::from_secs(1) {
+                last_report = Instant::now();
+                let elapsed = start.elapsed().as_secs_f64();
+                eprintln!(
+                    "games {games}/{total_games}, positions {positions}, {:.0} positions/s",
+                    positions as f64 / elapsed
+                );
+            }
+        }
+
+        output.flush().unwrap_or_else(|e| {
+            eprintln!("error: failed to write to {}: {e}", options.output);
+            exit(1);
+        });
+        let elapsed = start.elapsed().as_secs_f64();
+        eprintln!("done: {games} games, {positions} positions in {elapsed:.1}s");
+    });
+}
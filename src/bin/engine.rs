@@ -0,0 +1,177 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    process::exit,
+    time::Duration,
+};
+
+use ataxxgen::{
+    openings::Book,
+    rng::Rng,
+    search::{Limits, Search, SearchResult, SCORE_WIN, SCORE_WIN_BOUND},
+    Board, Move, Player, Square,
+};
+
+const USAGE: &str = "\
+usage: engine [options]
+
+A UAI engine built on the crate's alpha-beta search, for testing tools that drive
+engines over stdin and stdout.
+
+options:
+  --book <path>         play moves from a weighted book while the position is in it
+  --crash-after <n>     exit without answering the nth `go` of a game
+  --illegal-after <n>   answer the nth `go` of a game with an illegal move";
+
+#[derive(Default)]
+struct Options {
+    book: Option<Book>,
+    crash_after: Option<u64>,
+    illegal_after: Option<u64>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            exit(0);
+        }
+        let value = args.next().ok_or_else(|| format!("missing value for {arg}"))?;
+        let invalid = |_| format!("invalid value for {arg}: {value}");
+        match arg.as_str() {
+            "--book" => {
+                let book = File::open(&value).and_then(|file| Book::read(BufReader::new(file)));
+                options.book = Some(book.map_err(|e| format!("could not read {value}: {e}"))?);
+            }
+            "--crash-after" => options.crash_after = Some(value.parse().map_err(invalid)?),
+            "--illegal-after" => options.illegal_after = Some(value.parse().map_err(invalid)?),
+            _ => return Err(format!("unknown option: {arg}")),
+        }
+    }
+    Ok(options)
+}
+
+/// Parses the arguments of a `position` command.
+fn parse_position(args: &[&str]) -> Result<Board, String> {
+    let moves_at = args.iter().position(|&a| a == "moves").unwrap_or(args.len());
+    let mut board = match args.first() {
+        Some(&"startpos") => Board::default(),
+        Some(&"fen") => args[1..moves_at].join(" ").parse().map_err(|e| format!("invalid fen: {e}"))?,
+        _ => return Err("expected startpos or fen".into()),
+    };
+    for mv in args.iter().skip(moves_at + 1) {
+        let mv: Move = mv.parse().map_err(|e| format!("{e}: {mv}"))?;
+        board.make_move(mv);
+    }
+    Ok(board)
+}
+
+/// Works out the limits of a search from the arguments of a `go` command.
+fn parse_go(args: &[&str], turn: Player) -> Limits {
+    let mut limits = Limits::default();
+    let (mut time, mut inc) = (None, 0);
+    let mut args = args.iter();
+    while let Some(&arg) = args.next() {
+        let value = args.next().and_then(|v| v.parse::<u64>().ok());
+        match (arg, turn) {
+            ("wtime", Player::White) | ("btime", Player::Black) => time = value,
+            ("winc", Player::White) | ("binc", Player::Black) => inc = value.unwrap_or(0),
+            ("movetime", _) => limits.time = value.map(Duration::from_millis),
+            ("nodes", _) => limits.nodes = value,
+            ("depth", _) => limits.depth = value.map(|d| d.min(u64::from(u8::MAX)) as u8),
+            _ => {}
+        }
+    }
+    if let Some(time) = time {
+        // spend a small slice of what is left, and never more than half of it.
+        let budget = (time / 30 + inc / 2).min(time / 2);
+        limits.time = Some(Duration::from_millis(budget.max(1)));
+    }
+    limits
+}
+
+fn print_info(out: &mut impl Write, result: &SearchResult) -> io::Result<()> {
+    if result.score.abs() >= SCORE_WIN_BOUND {
+        // scores of forced results count plies from the root, and mate scores count moves.
+        let moves = (SCORE_WIN - result.score.abs() + 1) / 2;
+        let mate = if result.score > 0 { moves } else { -moves };
+        writeln!(out, "info depth {} score mate {mate} nodes {}", result.depth, result.nodes)?;
+    } else {
+        writeln!(out, "info depth {} score cp {} nodes {}", result.depth, result.score, result.nodes)?;
+    }
+    out.flush()
+}
+
+fn main() {
+    let options = parse_options().unwrap_or_else(|e| {
+        eprintln!("error: {e}\n\n{USAGE}");
+        exit(1);
+    });
+
+    let mut search = Search::new(16);
+    let mut rng = Rng::from_entropy();
+    let mut board = Board::default();
+    let mut gos = 0;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let result = match tokens.first().copied() {
+            Some("uai") => writeln!(
+                out,
+                "id name ataxxgen\nid author ataxxgen authors\noption name Hash type spin default 16 min 1 max 4096\nuaiok"
+            ),
+            Some("isready") => writeln!(out, "readyok"),
+            Some("setoption") => {
+                if let ["setoption", "name", "Hash", "value", mb] = tokens.as_slice() {
+                    if let Ok(mb) = mb.parse() {
+                        search = Search::new(mb);
+                    }
+                }
+                Ok(())
+            }
+            Some("uainewgame") => {
+                search.clear();
+                gos = 0;
+                Ok(())
+            }
+            Some("position") => {
+                match parse_position(&tokens[1..]) {
+                    Ok(position) => board = position,
+                    Err(e) => eprintln!("error: {e}"),
+                }
+                Ok(())
+            }
+            Some("go") => {
+                gos += 1;
+                if options.crash_after == Some(gos) {
+                    exit(3);
+                }
+                let book_move = options.book.as_ref().and_then(|book| book.pick(&board, &mut rng));
+                let mv = if options.illegal_after == Some(gos) {
+                    // a single move onto an occupied square is never legal.
+                    let occupied = Square::all().find(|&sq| board.player_at(sq).is_some());
+                    occupied.map_or("0000".to_owned(), |sq| sq.to_string())
+                } else if let Some(mv) = book_move {
+                    mv.to_string()
+                } else {
+                    let limits = parse_go(&tokens[1..], board.turn());
+                    let result = search.search_with_info(&board, limits, |r| {
+                        let _ = print_info(&mut out, r);
+                    });
+                    result.best_move.map_or("0000".to_owned(), |mv| mv.to_string())
+                };
+                writeln!(out, "bestmove {mv}")
+            }
+            Some("quit") => break,
+            _ => Ok(()),
+        };
+        if result.and_then(|_| out.flush()).is_err() {
+            break;
+        }
+    }
+}
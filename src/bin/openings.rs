@@ -0,0 +1,176 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use ataxxgen::{
+    dataset::Dedup,
+    openings::{enumerate, filter_by_score, load_openings, sample, write_epd, Book},
+    rng::Rng,
+    search::{Limits, Search},
+    walls::{self, random_walls, LAYOUTS},
+    Board,
+};
+
+const USAGE: &str = "\
+usage: openings --output <path> [options]
+
+Generates an opening book of short lines from the start position, from wall layouts or
+from the positions of a root file, and writes the positions they reach.
+
+options:
+  --output <path>       EPD file to write the openings to, with their moves and scores
+  --book <path>         also write a weighted book of the openings' moves, keyed by position
+  --roots <path>        FEN or EPD file of positions to start from
+  --walls <names>       comma-separated standard wall layouts to start from, or `all`
+  --random-walls <n>    start from n random wall layouts, symmetric under the symmetries
+                        of the board
+  --max-walls <n>       most walls in a random layout [8]
+  --plies <n>           length of the lines [4]
+  --sample <n>          pick n random lines rather than enumerating every line
+  --dedup <mode>        `off`, `position`, or `symmetric` to fold board symmetries [position]
+  --window <n>          keep only openings a short search scores within this many
+                        evaluation units of even
+  --nodes <n>           node limit of the search used by --window [5000]
+  --threads <n>         number of search threads [1]
+  --tt-mb <n>           transposition table size per thread [16]
+  --seed <n>            seed for --sample and --random-walls [from the clock]";
+
+struct Options {
+    output: PathBuf,
+    book: Option<PathBuf>,
+    roots: Option<PathBuf>,
+    layouts: Vec<Board>,
+    random_walls: usize,
+    max_walls: u32,
+    plies: u32,
+    sample: Option<usize>,
+    dedup: Dedup,
+    window: Option<i32>,
+    nodes: u64,
+    threads: usize,
+    tt_mb: usize,
+    seed: u64,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        output: PathBuf::new(),
+        book: None,
+        roots: None,
+        layouts: Vec::new(),
+        random_walls: 0,
+        max_walls: 8,
+        plies: 4,
+        sample: None,
+        dedup: Dedup::Position,
+        window: None,
+        nodes: 5000,
+        threads: 1,
+        tt_mb: 16,
+        seed: Rng::from_entropy().next_u64(),
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            exit(0);
+        }
+        let value = args.next().ok_or_else(|| format!("missing value for {arg}"))?;
+        let invalid = |_| format!("invalid value for {arg}: {value}");
+        match arg.as_str() {
+            "--output" => options.output = PathBuf::from(&value),
+            "--book" => options.book = Some(PathBuf::from(&value)),
+            "--roots" => options.roots = Some(PathBuf::from(&value)),
+            "--walls" if value == "all" => options.layouts.extend(LAYOUTS.iter().map(|layout| layout.board())),
+            "--walls" => {
+                for name in value.split(',') {
+                    let layout = walls::layout(name).ok_or_else(|| format!("unknown wall layout: {name}"))?;
+                    options.layouts.push(layout.board());
+                }
+            }
+            "--random-walls" => options.random_walls = value.parse().map_err(invalid)?,
+            "--max-walls" => options.max_walls = value.parse().map_err(invalid)?,
+            "--plies" => options.plies = value.parse().map_err(invalid)?,
+            "--sample" => options.sample = Some(value.parse().map_err(invalid)?),
+            "--dedup" => {
+                options.dedup = match value.as_str() {
+                    "off" => Dedup::Off,
+                    "position" => Dedup::Position,
+                    "symmetric" => Dedup::Symmetric,
+                    _ => return Err(format!("unknown dedup mode: {value}")),
+                }
+            }
+            "--window" => options.window = Some(value.parse().map_err(invalid)?),
+            "--nodes" => options.nodes = value.parse().map_err(invalid)?,
+            "--threads" => options.threads = value.parse().map_err(invalid)?,
+            "--tt-mb" => options.tt_mb = value.parse().map_err(invalid)?,
+            "--seed" => options.seed = value.parse().map_err(invalid)?,
+            _ => return Err(format!("unknown option: {arg}")),
+        }
+    }
+
+    if options.output.as_os_str().is_empty() {
+        return Err("no output file given".into());
+    }
+    if options.threads == 0 {
+        return Err("need at least one thread".into());
+    }
+    Ok(options)
+}
+
+fn create(path: &Path) -> BufWriter<File> {
+    BufWriter::new(File::create(path).unwrap_or_else(|e| {
+        eprintln!("error: could not create {}: {e}", path.display());
+        exit(1);
+    }))
+}
+
+fn main() {
+    let options = parse_options().unwrap_or_else(|e| {
+        eprintln!("error: {e}\n\n{USAGE}");
+        exit(1);
+    });
+
+    let mut rng = Rng::new(options.seed);
+    let mut roots = match &options.roots {
+        Some(path) => load_openings(path).unwrap_or_else(|e| {
+            eprintln!("error: could not read {}: {e}", path.display());
+            exit(1);
+        }),
+        None => Vec::new(),
+    };
+    roots.extend_from_slice(&options.layouts);
+    roots.extend((0..options.random_walls).map(|_| Board::with_walls(random_walls(&mut rng, options.max_walls))));
+    if roots.is_empty() {
+        roots.push(Board::default());
+    }
+
+    let mut openings = match options.sample {
+        Some(count) => sample(&roots, options.plies, count, options.dedup, &mut rng),
+        None => enumerate(&roots, options.plies, options.dedup),
+    };
+    eprintln!("generated {} openings", openings.len());
+
+    if let Some(window) = options.window {
+        let mut searches: Vec<Search> = (0..options.threads).map(|_| Search::new(options.tt_mb)).collect();
+        openings = filter_by_score(openings, &mut searches, Limits::nodes(options.nodes), window);
+        eprintln!("kept {} openings within {window} evaluation units", openings.len());
+    }
+
+    write_epd(&mut create(&options.output), &openings).unwrap_or_else(|e| {
+        eprintln!("error: failed to write {}: {e}", options.output.display());
+        exit(1);
+    });
+    if let Some(path) = &options.book {
+        let book = Book::from_openings(&openings);
+        book.write(&mut create(path)).unwrap_or_else(|e| {
+            eprintln!("error: failed to write {}: {e}", path.display());
+            exit(1);
+        });
+        eprintln!("wrote a book of {} positions", book.len());
+    }
+}
@@ -0,0 +1,93 @@
+use std::{path::PathBuf, process::exit, time::Instant};
+
+use ataxxgen::{
+    rescore::{rescore_file, Options},
+    search::Limits,
+};
+
+const USAGE: &str = "\
+usage: rescore --input <path> --output <path> (--depth <n> | --nodes <n>) [options]
+
+Re-scores every position in a dataset with a fresh search, keeping the records in order.
+If the job is interrupted, running it again with the same arguments resumes it.
+
+options:
+  --input <path>        dataset to read
+  --output <path>       file to write the re-scored dataset to
+  --format <fmt>        `text` (fen | score | result lines) or `binary` (position records) [text]
+  --depth <n>           search each position to a fixed depth
+  --nodes <n>           search each position for a fixed number of nodes
+  --threads <n>         number of worker threads [1]
+  --tt-mb <n>           transposition table size per thread [16]
+  --batch-size <n>      positions per checkpoint [16384]
+  --checkpoint <path>   checkpoint file [<output>.checkpoint]";
+
+struct Args {
+    input: PathBuf,
+    output: PathBuf,
+    checkpoint: PathBuf,
+    options: Options,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut options = Options::default();
+    let mut input = None;
+    let mut output = None;
+    let mut checkpoint = None;
+    let mut limits = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            exit(0);
+        }
+        let value = args.next().ok_or_else(|| format!("missing value for {arg}"))?;
+        let invalid = |_| format!("invalid value for {arg}: {value}");
+        match arg.as_str() {
+            "--input" => input = Some(PathBuf::from(&value)),
+            "--output" => output = Some(PathBuf::from(&value)),
+            "--checkpoint" => checkpoint = Some(PathBuf::from(&value)),
+            "--format" => options.format = value.parse().map_err(|e| format!("{e}: {value}"))?,
+            "--depth" => limits = Some(Limits::depth(value.parse().map_err(invalid)?)),
+            "--nodes" => limits = Some(Limits::nodes(value.parse().map_err(invalid)?)),
+            "--threads" => options.threads = value.parse().map_err(invalid)?,
+            "--tt-mb" => options.tt_mb = value.parse().map_err(invalid)?,
+            "--batch-size" => options.batch_size = value.parse().map_err(invalid)?,
+            _ => return Err(format!("unknown option: {arg}")),
+        }
+    }
+
+    let (Some(input), Some(output), Some(limits)) = (input, output, limits) else {
+        return Err("--input, --output and one of --depth or --nodes are required".into());
+    };
+    options.limits = limits;
+    let checkpoint = checkpoint.unwrap_or_else(|| {
+        let mut path = output.clone();
+        path.as_mut_os_string().push(".checkpoint");
+        path
+    });
+    Ok(Args { input, output, checkpoint, options })
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("error: {e}\n\n{USAGE}");
+        exit(1);
+    });
+    if args.checkpoint.exists() {
+        eprintln!("resuming from {}", args.checkpoint.display());
+    }
+
+    let start = Instant::now();
+    let result = rescore_file(&args.input, &args.output, &args.checkpoint, &args.options, |done| {
+        eprintln!("{done} positions written ({:.1}s)", start.elapsed().as_secs_f64());
+    });
+    match result {
+        Ok(total) => eprintln!("done: {total} positions in {:.1}s", start.elapsed().as_secs_f64()),
+        Err(e) => {
+            eprintln!("error: {e}");
+            exit(1);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::{fs::File, io::BufWriter, path::PathBuf, process::exit, time::Instant};
+
+use ataxxgen::{
+    retro::{generate_layers, Index, LayeredTable, SymmetricIndex, Table, Wdl},
+    Board,
+};
+
+const USAGE: &str = "\
+usage: retro --output <path> [options]
+
+Solves every position of a small square board by retrograde analysis and writes a
+win/draw/loss table of them. A 5x5 board is solved a layer at a time into a directory,
+one file for each number of pieces, and an interrupted run can be resumed.
+
+options:
+  --output <path>       file to write the table to, or directory for a 5x5 board
+  --size <n>            side of the board, from 2 to 5 [4]
+  --threads <n>         number of worker threads [1]";
+
+struct Options {
+    output: PathBuf,
+    size: u8,
+    threads: usize,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options { output: PathBuf::new(), size: 4, threads: 1 };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            exit(0);
+        }
+        let value = args.next().ok_or_else(|| format!("missing value for {arg}"))?;
+        let invalid = |_| format!("invalid value for {arg}: {value}");
+        match arg.as_str() {
+            "--output" => options.output = PathBuf::from(&value),
+            "--size" => options.size = value.parse().map_err(invalid)?,
+            "--threads" => options.threads = value.parse().map_err(invalid)?,
+            _ => return Err(format!("unknown option: {arg}")),
+        }
+    }
+
+    if options.output.as_os_str().is_empty() {
+        return Err("no output file given".into());
+    }
+    if !(2..=5).contains(&options.size) {
+        return Err(format!("unsupported board size: {}", options.size));
+    }
+    if options.threads == 0 {
+        return Err("need at least one thread".into());
+    }
+    Ok(options)
+}
+
+fn main() {
+    let options = parse_options().unwrap_or_else(|e| {
+        eprintln!("error: {e}\n\n{USAGE}");
+        exit(1);
+    });
+
+    let start = Board::small(options.size);
+    if options.size == 5 {
+        solve_layered(&start, &options);
+        return;
+    }
+    let index = Index::new(start.walls()).expect("boards up to 4x4 can be indexed");
+    let timer = Instant::now();
+    let table = Table::generate(index, options.threads);
+    eprintln!("solved {} positions in {:.1}s", table.index().len(), timer.elapsed().as_secs_f64());
+
+    let mut counts = [0u64; 3];
+    for i in 0..table.index().len() {
+        counts[table.get(i) as usize] += 1;
+    }
+    eprintln!("{} wins, {} draws, {} losses", counts[0], counts[1], counts[2]);
+    report(table.probe(&start).expect("the start position is on the board"));
+
+    let mut out = BufWriter::new(File::create(&options.output).unwrap_or_else(|e| {
+        eprintln!("error: could not create {}: {e}", options.output.display());
+        exit(1);
+    }));
+    table.write(&mut out).unwrap_or_else(|e| {
+        eprintln!("error: failed to write {}: {e}", options.output.display());
+        exit(1);
+    });
+}
+
+fn solve_layered(start: &Board, options: &Options) {
+    let index = SymmetricIndex::new(start.walls()).expect("boards up to 5x5 can be indexed by symmetry");
+    let timer = Instant::now();
+    let progress = |pieces| eprintln!("solved the layer of {pieces} pieces at {:.1}s", timer.elapsed().as_secs_f64());
+    generate_layers(&index, &options.output, options.threads, progress).unwrap_or_else(|e| {
+        eprintln!("error: failed to write layers to {}: {e}", options.output.display());
+        exit(1);
+    });
+    let positions: u64 = (0..index.layers()).map(|pieces| index.layer_len(pieces)).sum();
+    eprintln!("solved {positions} positions in {:.1}s", timer.elapsed().as_secs_f64());
+
+    let value = LayeredTable::open(&options.output).and_then(|mut table| table.probe(start)).unwrap_or_else(|e| {
+        eprintln!("error: failed to read layers from {}: {e}", options.output.display());
+        exit(1);
+    });
+    report(value.expect("the start position is on the board"));
+}
+
+fn report(value: Wdl) {
+    let value = match value {
+        Wdl::Win => "a win",
+        Wdl::Draw => "a draw",
+        Wdl::Loss => "a loss",
+    };
+    eprintln!("the start position is {value} for the first player");
+}
@@ -0,0 +1,114 @@
+use std::{path::PathBuf, process::exit, time::Instant};
+
+use ataxxgen::{
+    dataset::{shuffle_split, Dedup, Options},
+    rng::Rng,
+};
+
+const USAGE: &str = "\
+usage: shuffle --train <path> [options] <input>...
+
+Removes duplicate positions from datasets, shuffles them in bounded memory and splits
+them into training and validation sets.
+
+options:
+  --train <path>             file to write the training set to
+  --validation <path>        file to write the validation set to
+  --validation-ratio <r>     fraction of positions for the validation set [0.05 with --validation]
+  --dedup <mode>             `off`, `position`, or `symmetric` to fold board symmetries [position]
+  --input-format <fmt>       `text` or `binary` [text]
+  --output-format <fmt>      `text` or `binary` [same as input]
+  --memory <n>               most positions held in memory at once [16777216]
+  --temp-dir <path>          directory for intermediate files [system temp dir]
+  --seed <n>                 shuffle seed [from the clock]";
+
+struct Args {
+    inputs: Vec<PathBuf>,
+    train: PathBuf,
+    validation: Option<PathBuf>,
+    options: Options,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut options = Options { seed: Rng::from_entropy().next_u64(), ..Options::default() };
+    let mut inputs = Vec::new();
+    let mut train = None;
+    let mut validation = None;
+    let mut validation_ratio = None;
+    let mut output_format = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            exit(0);
+        }
+        if !arg.starts_with("--") {
+            inputs.push(PathBuf::from(arg));
+            continue;
+        }
+        let value = args.next().ok_or_else(|| format!("missing value for {arg}"))?;
+        let invalid = |_| format!("invalid value for {arg}: {value}");
+        match arg.as_str() {
+            "--train" => train = Some(PathBuf::from(&value)),
+            "--validation" => validation = Some(PathBuf::from(&value)),
+            "--validation-ratio" => {
+                validation_ratio = Some(value.parse::<f64>().map_err(|_| format!("invalid value for {arg}: {value}"))?)
+            }
+            "--dedup" => {
+                options.dedup = match value.as_str() {
+                    "off" => Dedup::Off,
+                    "position" => Dedup::Position,
+                    "symmetric" => Dedup::Symmetric,
+                    _ => return Err(format!("unknown dedup mode: {value}")),
+                }
+            }
+            "--input-format" => options.input_format = value.parse().map_err(|e| format!("{e}: {value}"))?,
+            "--output-format" => output_format = Some(value.parse().map_err(|e| format!("{e}: {value}"))?),
+            "--memory" => options.memory_records = value.parse().map_err(invalid)?,
+            "--temp-dir" => options.temp_dir = PathBuf::from(&value),
+            "--seed" => options.seed = value.parse().map_err(invalid)?,
+            _ => return Err(format!("unknown option: {arg}")),
+        }
+    }
+
+    let Some(train) = train else {
+        return Err("no training set output given".into());
+    };
+    if inputs.is_empty() {
+        return Err("no input files given".into());
+    }
+    options.output_format = output_format.unwrap_or(options.input_format);
+    options.validation_ratio = match (&validation, validation_ratio) {
+        (Some(_), ratio) => ratio.unwrap_or(0.05),
+        (None, Some(_)) => return Err("--validation-ratio needs --validation".into()),
+        (None, None) => 0.0,
+    };
+    if !(0.0..=1.0).contains(&options.validation_ratio) {
+        return Err("the validation ratio must be between 0 and 1".into());
+    }
+    Ok(Args { inputs, train, validation, options })
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("error: {e}\n\n{USAGE}");
+        exit(1);
+    });
+
+    let start = Instant::now();
+    match shuffle_split(&args.inputs, &args.train, args.validation.as_deref(), &args.options) {
+        Ok(summary) => eprintln!(
+            "read {} positions, dropped {} duplicates, wrote {} training and {} validation positions in {:.1}s",
+            summary.read,
+            summary.duplicates,
+            summary.train,
+            summary.validation,
+            start.elapsed().as_secs_f64()
+        ),
+        Err(e) => {
+            eprintln!("error: {e}");
+            exit(1);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::{fs::File, io::BufWriter, path::PathBuf, process::exit, time::Instant};
+
+use ataxxgen::{
+    openings::load_openings,
+    rng::Rng,
+    spsa::{default_params, Engine, EngineConfig, Options, Param, Spsa},
+};
+
+const USAGE: &str = "\
+usage: spsa --output <path> [options]
+
+Tunes engine parameters with SPSA by playing the engine against itself, and logs the
+parameter values after every iteration as CSV.
+
+options:
+  --output <path>      CSV file to write the trajectory to
+  --param <spec>       a parameter to tune, as name,value,min,max,c_end,r_end; may be
+                       repeated [every tunable parameter with its default schedule]
+  --openings <path>    FEN or EPD file of opening positions [random openings]
+  --random-plies <n>   random moves in each generated opening [4]
+  --engine <kind>      `alphabeta` or `mcts` [alphabeta]
+  --nodes <n>          nodes per move for alphabeta, visits per move for mcts [2000]
+  --iterations <n>     number of SPSA iterations [1000]
+  --pairs <n>          game pairs per iteration [1]
+  --threads <n>        number of worker threads [1]
+  --seed <n>           seed for perturbations and openings [from the clock]";
+
+struct Args {
+    output: PathBuf,
+    params: Vec<Param>,
+    openings: Option<PathBuf>,
+    options: Options,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut options = Options { seed: Rng::from_entropy().next_u64(), ..Options::default() };
+    let mut output = None;
+    let mut params = Vec::new();
+    let mut openings = None;
+    let mut mcts = false;
+    let mut nodes = 2_000;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            exit(0);
+        }
+        let value = args.next().ok_or_else(|| format!("missing value for {arg}"))?;
+        let invalid = |_| format!("invalid value for {arg}: {value}");
+        match arg.as_str() {
+            "--output" => output = Some(PathBuf::from(&value)),
+            "--param" => params.push(value.parse::<Param>()?),
+            "--openings" => openings = Some(PathBuf::from(&value)),
+            "--random-plies" => options.random_plies = value.parse().map_err(invalid)?,
+            "--engine" => {
+                mcts = match value.as_str() {
+                    "alphabeta" => false,
+                    "mcts" => true,
+                    _ => return Err(format!("unknown engine: {value}")),
+                }
+            }
+            "--nodes" => nodes = value.parse().map_err(invalid)?,
+            "--iterations" => options.iterations = value.parse().map_err(invalid)?,
+            "--pairs" => options.pairs = value.parse().map_err(invalid)?,
+            "--threads" => options.threads = value.parse().map_err(invalid)?,
+            "--seed" => options.seed = value.parse().map_err(invalid)?,
+            _ => return Err(format!("unknown option: {arg}")),
+        }
+    }
+
+    let Some(output) = output else {
+        return Err("no output file given".into());
+    };
+    if options.threads == 0 {
+        return Err("need at least one thread".into());
+    }
+    options.engine = if mcts { Engine::Mcts { visits: nodes } } else { Engine::AlphaBeta { nodes } };
+    if params.is_empty() {
+        params = default_params();
+    }
+    Ok(Args { output, params, openings, options })
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("error: {e}\n\n{USAGE}");
+        exit(1);
+    });
+
+    let openings = match &args.openings {
+        Some(path) => load_openings(path).unwrap_or_else(|e| {
+            eprintln!("error: could not read {}: {e}", path.display());
+            exit(1);
+        }),
+        None => Vec::new(),
+    };
+    let mut spsa = Spsa::new(args.params, EngineConfig::default(), openings, args.options).unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        exit(1);
+    });
+
+    let file = File::create(&args.output).unwrap_or_else(|e| {
+        eprintln!("error: could not create {}: {e}", args.output.display());
+        exit(1);
+    });
+    let start = Instant::now();
+    let result = spsa.run(&mut BufWriter::new(file), |spsa, result| {
+        let values: Vec<String> = spsa.params().iter().map(|p| format!("{}={:.3}", p.name, p.value)).collect();
+        eprintln!(
+            "iteration {}/{} ({:+}) {} [{:.1}s]",
+            spsa.iteration(),
+            args.options.iterations,
+            result,
+            values.join(" "),
+            start.elapsed().as_secs_f64()
+        );
+    });
+    if let Err(e) = result {
+        eprintln!("error: failed to write to {}: {e}", args.output.display());
+        exit(1);
+    }
+    for param in spsa.params() {
+        println!("{param}");
+    }
+}
@@ -0,0 +1,97 @@
+use std::{path::PathBuf, process::exit, time::Instant};
+
+use ataxxgen::{
+    eval::DEFAULT_WEIGHTS,
+    record::Format,
+    tune::{load_positions, weights_to_rust, Tuner},
+};
+
+const USAGE: &str = "\
+usage: tune [options] <input>...
+
+Tunes the evaluation weights against the results of labelled positions by minimising
+the error of the sigmoid-scaled evaluation, and prints the weights as Rust source.
+
+options:
+  --format <fmt>     `text` or `binary` [text]
+  --threads <n>      number of worker threads [1]
+  --epochs <n>       gradient descent steps [1000]
+  --lr <x>           learning rate [1.0]
+  --k <x>            sigmoid scaling constant [fitted to the default weights]";
+
+struct Options {
+    inputs: Vec<PathBuf>,
+    format: Format,
+    threads: usize,
+    epochs: usize,
+    learning_rate: f64,
+    k: Option<f64>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options =
+        Options { inputs: Vec::new(), format: Format::Text, threads: 1, epochs: 1000, learning_rate: 1.0, k: None };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            exit(0);
+        }
+        if !arg.starts_with("--") {
+            options.inputs.push(PathBuf::from(arg));
+            continue;
+        }
+        let value = args.next().ok_or_else(|| format!("missing value for {arg}"))?;
+        let invalid = || format!("invalid value for {arg}: {value}");
+        match arg.as_str() {
+            "--format" => options.format = value.parse().map_err(|e| format!("{e}: {value}"))?,
+            "--threads" => options.threads = value.parse().map_err(|_| invalid())?,
+            "--epochs" => options.epochs = value.parse().map_err(|_| invalid())?,
+            "--lr" => options.learning_rate = value.parse().map_err(|_| invalid())?,
+            "--k" => options.k = Some(value.parse().map_err(|_| invalid())?),
+            _ => return Err(format!("unknown option: {arg}")),
+        }
+    }
+
+    if options.inputs.is_empty() {
+        return Err("no input files given".into());
+    }
+    if options.threads == 0 {
+        return Err("need at least one thread".into());
+    }
+    Ok(options)
+}
+
+fn main() {
+    let options = parse_options().unwrap_or_else(|e| {
+        eprintln!("error: {e}\n\n{USAGE}");
+        exit(1);
+    });
+
+    let mut positions = Vec::new();
+    for input in &options.inputs {
+        positions.extend(load_positions(input, options.format).unwrap_or_else(|e| {
+            eprintln!("error: could not read {}: {e}", input.display());
+            exit(1);
+        }));
+    }
+    if positions.is_empty() {
+        eprintln!("error: no positions with known results");
+        exit(1);
+    }
+    eprintln!("loaded {} positions", positions.len());
+
+    let tuner = Tuner::new(positions, options.threads);
+    let weights = DEFAULT_WEIGHTS.map(f64::from);
+    let k = options.k.unwrap_or_else(|| tuner.fit_k(&weights));
+    eprintln!("k = {k:.4}, initial error {:.6}", tuner.error(&weights, k));
+
+    let start = Instant::now();
+    let tuned = tuner.tune(weights, k, options.epochs, options.learning_rate, |epoch, error, _| {
+        if epoch % 50 == 0 || epoch == options.epochs {
+            eprintln!("epoch {epoch}: error {error:.6} ({:.1}s)", start.elapsed().as_secs_f64());
+        }
+    });
+    print!("{}", weights_to_rust(&tuned));
+}
@@ -0,0 +1,319 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    mix,
+    record::{DatasetReader, DatasetWriter, Format, PositionRecord, RecordReader, RecordWriter, RECORD_SIZE},
+    rng::Rng,
+};
+
+/// The most bucket files open at once while splitting.
+const MAX_BUCKETS: usize = 256;
+
+/// Which positions count as duplicates of one another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dedup {
+    /// Keep every record.
+    Off,
+    /// Keep the first record of each position (pieces, walls and side to move).
+    Position,
+    /// Keep the first record of each position up to the symmetries of the board.
+    Symmetric,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub input_format: Format,
+    pub output_format: Format,
+    pub dedup: Dedup,
+    /// The fraction of records written to the validation set.
+    pub validation_ratio: f64,
+    /// The most records held in memory at once.
+    pub memory_records: usize,
+    pub seed: u64,
+    /// Where to keep the intermediate bucket files.
+    pub temp_dir: PathBuf,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            input_format: Format::Text,
+            output_format: Format::Text,
+            dedup: Dedup::Position,
+            validation_ratio: 0.0,
+            memory_records: 1 << 24,
+            seed: 0,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub read: u64,
+    pub duplicates: u64,
+    pub train: u64,
+    pub validation: u64,
+}
+
+type BucketWriter = RecordWriter<BufWriter<File>>;
+
+struct Job<'a> {
+    options: &'a Options,
+    dir: PathBuf,
+    rng: Rng,
+    train: DatasetWriter<BufWriter<File>>,
+    validation: Option<DatasetWriter<BufWriter<File>>>,
+    summary: Summary,
+}
+
+impl Job<'_> {
+    fn key(&self, record: &PositionRecord) -> Option<u64> {
+        match self.options.dedup {
+            Dedup::Off => None,
+            Dedup::Position => Some(record.board.key()),
+            Dedup::Symmetric => Some(record.board.canonical_key()),
+        }
+    }
+
+    /// Picks the bucket of a record at a given level of splitting. Duplicates must land in the
+    /// same bucket, so when deduplicating the bucket comes from a salted hash of the key, which
+    /// is as good as random for distinct positions.
+    fn bucket(&mut self, record: &PositionRecord, level: u32, buckets: usize) -> usize {
+        let hash = match self.key(record) {
+            Some(key) => mix(key ^ mix(self.options.seed.wrapping_add(u64::from(level)))),
+            None => self.rng.next_u64(),
+        };
+        ((u128::from(hash) * buckets as u128) >> 64) as usize
+    }
+
+    fn create_buckets(&self, level: u32, count: usize, prefix: &str) -> io::Result<Vec<(PathBuf, BucketWriter)>> {
+        (0..count)
+            .map(|i| {
+                let path = self.dir.join(format!("{prefix}-{level}-{i}"));
+                let writer = RecordWriter::new(BufWriter::new(File::create(&path)?));
+                Ok((path, writer))
+            })
+            .collect()
+    }
+
+    /// Scatters `records` between the `buckets` of the given level, returning how many there were.
+    fn scatter(
+        &mut self,
+        records: impl Iterator<Item = io::Result<PositionRecord>>,
+        buckets: &mut [(PathBuf, BucketWriter)],
+        level: u32,
+    ) -> io::Result<u64> {
+        let mut count = 0;
+        for record in records {
+            let record = record?;
+            let bucket = self.bucket(&record, level, buckets.len());
+            buckets[bucket].1.write_record(&record)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn finish_buckets(buckets: Vec<(PathBuf, BucketWriter)>) -> io::Result<Vec<PathBuf>> {
+        buckets
+            .into_iter()
+            .map(|(path, mut writer)| {
+                writer.flush()?;
+                Ok(path)
+            })
+            .collect()
+    }
+
+    /// Deduplicates and shuffles one bucket and writes it out, splitting it further first if
+    /// it doesn't fit in memory.
+    fn process(&mut self, path: &Path, level: u32) -> io::Result<()> {
+        let count = fs::metadata(path)?.len() as usize / RECORD_SIZE;
+        let reader = || -> io::Result<_> { Ok(RecordReader::new(BufReader::new(File::open(path)?))) };
+
+        if count > self.options.memory_records.max(1) && level < 8 {
+            let buckets = count.div_ceil(self.options.memory_records.max(1)).clamp(2, MAX_BUCKETS);
+            let prefix = path.file_name().and_then(|n| n.to_str()).unwrap_or("bucket").to_owned();
+            let mut children = self.create_buckets(level + 1, buckets, &prefix)?;
+            self.scatter(reader()?, &mut children, level + 1)?;
+            fs::remove_file(path)?;
+            for child in Self::finish_buckets(children)? {
+                self.process(&child, level + 1)?;
+            }
+            return Ok(());
+        }
+
+        // a bucket that is still too big after splitting is mostly duplicates, so only reserve
+        // what fits in memory.
+        let mut records = Vec::with_capacity(count.min(self.options.memory_records));
+        let mut seen = HashSet::new();
+        for record in reader()? {
+            let record = record?;
+            if let Some(key) = self.key(&record) {
+                if !seen.insert(key) {
+                    self.summary.duplicates += 1;
+                    continue;
+                }
+            }
+            records.push(record);
+        }
+        fs::remove_file(path)?;
+
+        for i in (1..records.len()).rev() {
+            records.swap(i, self.rng.range(0, i + 1));
+        }
+
+        for record in &records {
+            // spread validation records evenly through the output rather than sampling them.
+            let written = self.summary.train + self.summary.validation + 1;
+            let due = (written as f64 * self.options.validation_ratio + 1e-9) as u64;
+            match &mut self.validation {
+                Some(validation) if due > self.summary.validation => {
+                    validation.write_record(record)?;
+                    self.summary.validation += 1;
+                }
+                _ => {
+                    self.train.write_record(record)?;
+                    self.summary.train += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads every record from `inputs`, removes duplicates, shuffles them and writes them to
+/// `train` and, if given, `validation`.
+///
+/// Memory use is bounded by `options.memory_records`: records are first scattered to bucket
+/// files in `options.temp_dir` by a seeded hash, and each bucket is then deduplicated and
+/// shuffled in memory, being split again first if it is still too large. Of any duplicates,
+/// the first one read is kept.
+pub fn shuffle_split(
+    inputs: &[PathBuf],
+    train: &Path,
+    validation: Option<&Path>,
+    options: &Options,
+) -> io::Result<Summary> {
+    let dir = options.temp_dir.join(format!("ataxxgen-shuffle-{}-{:x}", std::process::id(), options.seed));
+    fs::create_dir_all(&dir)?;
+
+    let mut job = Job {
+        options,
+        dir: dir.clone(),
+        rng: Rng::new(options.seed),
+        train: DatasetWriter::new(BufWriter::new(File::create(train)?), options.output_format),
+        validation: match validation {
+            Some(path) => Some(DatasetWriter::new(BufWriter::new(File::create(path)?), options.output_format)),
+            None => None,
+        },
+        summary: Summary::default(),
+    };
+
+    let result = (|| {
+        let bytes_per_record = match options.input_format {
+            Format::Binary => RECORD_SIZE as u64,
+            // a deliberately low estimate of a text line, so that buckets err on the small side.
+            Format::Text => 40,
+        };
+        let mut estimate = 0;
+        for input in inputs {
+            estimate += fs::metadata(input)?.len() / bytes_per_record;
+        }
+        let buckets = (estimate as usize).div_ceil(options.memory_records.max(1)).clamp(1, MAX_BUCKETS);
+
+        let mut paths = job.create_buckets(0, buckets, "bucket")?;
+        for input in inputs {
+            let reader = DatasetReader::new(BufReader::new(File::open(input)?), options.input_format);
+            job.summary.read += job.scatter(reader, &mut paths, 0)?;
+        }
+        let paths = Job::finish_buckets(paths)?;
+
+        for path in paths {
+            job.process(&path, 0)?;
+        }
+        job.train.flush()?;
+        if let Some(validation) = &mut job.validation {
+            validation.flush()?;
+        }
+        Ok(job.summary)
+    })();
+
+    // a leftover temporary directory is harmless, so failing to remove it must not hide the result.
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shuffle_split, Dedup, Options};
+    use crate::{
+        record::{DatasetReader, DatasetWriter, Format, PositionRecord},
+        rng::Rng,
+        Board,
+    };
+    use std::{collections::HashSet, fs::File, io::BufReader};
+
+    #[test]
+    fn dedups_shuffles_and_splits() {
+        let dir = std::env::temp_dir().join(format!("ataxxgen-dataset-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("in.txt");
+
+        // a few short random games from the start position, which transpose heavily.
+        let mut rng = Rng::new(5);
+        let mut writer = DatasetWriter::new(File::create(&input).unwrap(), Format::Text);
+        let mut inputs = Vec::new();
+        for _ in 0..50 {
+            let mut board = Board::default();
+            for _ in 0..4 {
+                let record = PositionRecord { board, score: Some(0), result: Some(None) };
+                writer.write_record(&record).unwrap();
+                inputs.push(record);
+                board.make_random_move(|lo, hi| rng.range(lo, hi));
+            }
+        }
+        drop(writer);
+
+        let unique: HashSet<u64> = inputs.iter().map(|r| r.board.key()).collect();
+        let canonical: HashSet<u64> = inputs.iter().map(|r| r.board.canonical_key()).collect();
+        assert!(canonical.len() < unique.len() && unique.len() < inputs.len());
+
+        let (train, validation) = (dir.join("train.bin"), dir.join("val.bin"));
+        let read_back = |path| {
+            DatasetReader::new(BufReader::new(File::open(path).unwrap()), Format::Binary)
+                .collect::<std::io::Result<Vec<_>>>()
+                .unwrap()
+        };
+        for (dedup, expected) in [(Dedup::Off, inputs.len()), (Dedup::Position, unique.len()), (Dedup::Symmetric, canonical.len())] {
+            let options = Options {
+                input_format: Format::Text,
+                output_format: Format::Binary,
+                dedup,
+                validation_ratio: 0.1,
+                memory_records: 16,
+                seed: 9,
+                temp_dir: dir.clone(),
+            };
+            let summary = shuffle_split(std::slice::from_ref(&input), &train, Some(&validation), &options).unwrap();
+            assert_eq!(summary.read, inputs.len() as u64);
+            assert_eq!((summary.train + summary.validation) as usize, expected);
+            assert_eq!(summary.duplicates as usize, inputs.len() - expected);
+            assert_eq!(summary.validation as usize, expected / 10);
+
+            let mut out = read_back(&train);
+            out.extend(read_back(&validation));
+            assert_eq!(out.len(), expected);
+            if dedup == Dedup::Position {
+                let keys: HashSet<u64> = out.iter().map(|r| r.board.key()).collect();
+                assert_eq!(keys, unique);
+            }
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
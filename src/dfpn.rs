@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+
+use crate::{endgame::table_key, Board, Move, Player};
+
+/// Proof and disproof numbers at least this large mean the proof is impossible.
+const INFINITY: u64 = u64::MAX / 4;
+
+/// The default for [`Dfpn::max_entries`], around 50 MB of proof numbers.
+pub const DEFAULT_MAX_ENTRIES: usize = 1 << 20;
+
+/// What a proof-number search found out about a position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Proof {
+    /// The side to move wins.
+    Proven,
+    /// The side to move cannot force a win: it loses or draws.
+    Disproven,
+    /// The node limit was reached first.
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DfpnResult {
+    pub proof: Proof,
+    /// A winning move, if the position was proven.
+    pub mv: Option<Move>,
+    pub nodes: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    pn: u64,
+    dn: u64,
+    /// The child proving the position, at nodes where the attacker is to move.
+    mv: Option<Move>,
+}
+
+/// Depth-first proof-number search, which proves or disproves that the side to move can
+/// force a win, however long it takes.
+///
+/// Proof and disproof numbers are kept between calls, keyed by position and halfmove clock,
+/// as long as the side proving a win stays the same, until there are `max_entries` of them
+/// and the table is emptied.
+pub struct Dfpn {
+    pub max_nodes: Option<u64>,
+    /// The most proof and disproof numbers kept at once.
+    pub max_entries: usize,
+    table: HashMap<u64, Entry>,
+    /// The side trying to prove a win, which moves at OR nodes.
+    attacker: Player,
+    nodes: u64,
+}
+
+impl Default for Dfpn {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dfpn {
+    pub fn new() -> Self {
+        Self {
+            max_nodes: None,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            table: HashMap::new(),
+            attacker: Player::White,
+            nodes: 0,
+        }
+    }
+
+    /// Forgets the results of previous calls.
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    pub fn solve(&mut self, board: &Board) -> DfpnResult {
+        if board.turn() != self.attacker {
+            self.attacker = board.turn();
+            self.table.clear();
+        }
+        self.nodes = 0;
+        let (pn, dn) = self.mid(board, INFINITY, INFINITY);
+        let proof = if pn == 0 {
+            Proof::Proven
+        } else if dn == 0 {
+            Proof::Disproven
+        } else {
+            Proof::Unknown
+        };
+        let mv = if proof == Proof::Proven { self.table.get(&table_key(board)).and_then(|e| e.mv) } else { None };
+        DfpnResult { proof, mv, nodes: self.nodes }
+    }
+
+    fn out_of_nodes(&self) -> bool {
+        self.max_nodes.is_some_and(|n| self.nodes >= n)
+    }
+
+    /// The proof and disproof numbers of a position that has not been expanded yet.
+    fn lookup(&self, board: &Board) -> (u64, u64) {
+        if let Some(outcome) = board.outcome() {
+            return if outcome == Some(self.attacker) { (0, INFINITY) } else { (INFINITY, 0) };
+        }
+        self.table.get(&table_key(board)).map_or((1, 1), |e| (e.pn, e.dn))
+    }
+
+    /// Expands `board` until its proof number reaches `thpn` or its disproof number reaches
+    /// `thdn`, returning both.
+    fn mid(&mut self, board: &Board, thpn: u64, thdn: u64) -> (u64, u64) {
+        self.nodes += 1;
+        let terminal = self.lookup(board);
+        if board.game_over() {
+            return terminal;
+        }
+
+        let or_node = board.turn() == self.attacker;
+        let mut children = Vec::new();
+        board.generate_moves(|mv| {
+            let mut child = *board;
+            child.make_move(mv);
+            children.push((mv, child));
+            false
+        });
+
+        loop {
+            // at OR nodes the attacker needs one proven child and at AND nodes all of them,
+            // so `first` is the number that takes the minimum and `second` the one that adds up.
+            let (mut best, mut min, mut second_min) = (0, INFINITY, INFINITY);
+            let (mut max_second, mut open) = (0, 0);
+            for (i, (_, child)) in children.iter().enumerate() {
+                let (pn, dn) = self.lookup(child);
+                let (first, second) = if or_node { (pn, dn) } else { (dn, pn) };
+                if first < min {
+                    second_min = min;
+                    min = first;
+                    best = i;
+                } else if first < second_min {
+                    second_min = first;
+                }
+                max_second = max_second.max(second);
+                open += u64::from(second > 0);
+            }
+            // doubles reach the same positions by many paths, which makes true sums grow
+            // exponentially, so the largest plus one for each other open child stands in.
+            let sum = if max_second >= INFINITY {
+                INFINITY
+            } else {
+                (max_second + open.saturating_sub(1)).min(INFINITY - 1)
+            };
+            let (pn, dn) = if or_node { (min, sum) } else { (sum, min) };
+
+            if pn >= thpn || dn >= thdn || self.out_of_nodes() {
+                let mv = (or_node && pn == 0).then_some(children[best].0);
+                let key = table_key(board);
+                if self.table.len() >= self.max_entries && !self.table.contains_key(&key) {
+                    self.table.clear();
+                }
+                self.table.insert(key, Entry { pn, dn, mv });
+                return (pn, dn);
+            }
+
+            let (child_pn, child_dn) = self.lookup(&children[best].1);
+            let (child_thpn, child_thdn) = if or_node {
+                (thpn.min(second_min.saturating_add(1)), thdn - dn + child_dn)
+            } else {
+                (thpn - pn + child_pn, thdn.min(second_min.saturating_add(1)))
+            };
+            let child = children[best].1;
+            self.mid(&child, child_thpn, child_thdn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dfpn, Proof};
+    use crate::{endgame::Solver, rng::Rng, Board, Move};
+
+    #[test]
+    fn proves_wins_that_need_a_pass() {
+        let mut dfpn = Dfpn::new();
+        // white cannot move, but stays ahead after black fills the last square.
+        let ahead: Board = "xxxxxxx/xxxxxxx/xxxxxxx/xxxxxxx/ooooooo/ooooooo/oooooo1 x 0 1".parse().unwrap();
+        let result = dfpn.solve(&ahead);
+        assert_eq!((result.proof, result.mv), (Proof::Proven, Some(Move::Pass)));
+
+        let behind: Board = "xxxxxxx/xxxxxxx/xxxxxxx/ooooooo/ooooooo/ooooooo/oooooo1 x 0 1".parse().unwrap();
+        assert_eq!(dfpn.solve(&behind).proof, Proof::Disproven);
+
+        dfpn.max_nodes = Some(100);
+        assert_eq!(dfpn.solve(&Board::default()).proof, Proof::Unknown);
+    }
+
+    #[test]
+    fn agrees_with_the_endgame_solver() {
+        // a 3x3 board in the corner, and positions near the end of full-size games.
+        let mut boards = vec!["x1o----/3----/3----/-------/-------/-------/------- x 0 1".parse::<Board>().unwrap()];
+        let mut rng = Rng::new(11);
+        while boards.len() < 16 {
+            let mut board = Board::default();
+            while !board.game_over() && board.empty_count() > 1 {
+                board.make_random_move(|lo, hi| rng.range(lo, hi));
+            }
+            let fen = board.fen();
+            let parts: Vec<&str> = fen.split(' ').collect();
+            let board: Board = format!("{} {} 97 {}", parts[0], parts[1], parts[3]).parse().unwrap();
+            if !board.game_over() {
+                boards.push(board);
+            }
+        }
+
+        let mut solver = Solver::new(9);
+        for board in boards {
+            let mut dfpn = Dfpn::new();
+            let result = dfpn.solve(&board);
+            let mut bounded = Dfpn { max_entries: 16, ..Dfpn::new() };
+            assert_eq!(bounded.solve(&board).proof, result.proof, "{}", board.fen());
+            assert!(bounded.table.len() <= 16);
+            let score = solver.solve(&board).unwrap().score;
+            assert_eq!(result.proof == Proof::Proven, score > 0, "{}", board.fen());
+            assert_eq!(result.proof == Proof::Disproven, score <= 0, "{}", board.fen());
+            if let Some(mv) = result.mv {
+                let mut after = board;
+                after.make_move(mv);
+                assert!(-solver.solve(&after).unwrap().score > 0, "{}", board.fen());
+            }
+        }
+    }
+}
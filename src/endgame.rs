@@ -0,0 +1,306 @@
+use std::{collections::HashMap, time::Instant};
+
+use crate::{expand, search::SCORE_SOLVED_BOUND, Board, Move, Player};
+
+/// Larger than any disc difference.
+const INFINITY: i32 = 100;
+
+/// The default for [`Solver::max_entries`], around 50 MB of results.
+pub const DEFAULT_MAX_ENTRIES: usize = 1 << 20;
+
+/// The exact result of a position under perfect play.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Solution {
+    /// The final disc difference from the side to move's perspective.
+    pub score: i32,
+    /// A move that reaches `score`, or `None` if the game is over.
+    pub best_move: Option<Move>,
+    pub nodes: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    lower: i32,
+    upper: i32,
+    mv: Option<Move>,
+}
+
+/// The difference between the side to move's pieces and the opponent's.
+pub fn disc_difference(board: &Board) -> i32 {
+    let diff = board.white.count_ones() as i32 - board.black.count_ones() as i32;
+    match board.turn() {
+        Player::White => diff,
+        Player::Black => -diff,
+    }
+}
+
+/// The search score of a solved position with final disc difference `diff`: a forced win or
+/// loss, better the more pieces it wins by. Its distance is not known, so it lies in the band
+/// from `SCORE_SOLVED_BOUND` up to, but not reaching, the mate scores.
+pub fn search_score(diff: i32) -> i32 {
+    match diff.signum() {
+        1 => SCORE_SOLVED_BOUND + diff,
+        -1 => -SCORE_SOLVED_BOUND + diff,
+        _ => 0,
+    }
+}
+
+/// Solves positions with few empty squares exactly, by searching every line to the end of
+/// the game with alpha-beta on the final disc difference.
+///
+/// Doubles leave the number of empty squares unchanged, so lines are only bounded by the
+/// 50-move rule. Where both players can make doubles inside their own territory to win a
+/// tempo, even two empty squares can take a long time to solve, so set `max_nodes` or use
+/// `solve_within` when solving during play. Results are kept between calls, keyed by position
+/// and halfmove clock, until there are `max_entries` of them and the table is emptied.
+pub struct Solver {
+    /// The most empty squares a position may have to be solved.
+    pub max_empties: u32,
+    /// Gives up on a position after this many nodes.
+    pub max_nodes: Option<u64>,
+    /// The most results kept at once.
+    pub max_entries: usize,
+    table: HashMap<u64, Entry>,
+    nodes: u64,
+    node_limit: Option<u64>,
+    deadline: Option<Instant>,
+    aborted: bool,
+}
+
+impl Solver {
+    pub fn new(max_empties: u32) -> Self {
+        Self {
+            max_empties,
+            max_nodes: None,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            table: HashMap::new(),
+            nodes: 0,
+            node_limit: None,
+            deadline: None,
+            aborted: false,
+        }
+    }
+
+    /// Whether `board` has few enough empty squares to be solved.
+    pub fn applies(&self, board: &Board) -> bool {
+        board.empty_count() <= self.max_empties
+    }
+
+    /// Forgets the results of previous calls.
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    /// Solves `board`, whatever its number of empty squares. Returns `None` if the node limit
+    /// is reached first.
+    pub fn solve(&mut self, board: &Board) -> Option<Solution> {
+        self.solve_within(board, None, None)
+    }
+
+    /// Like `solve`, but also gives up after `max_nodes` nodes, whichever of it and the
+    /// solver's own `max_nodes` is lower, or once `deadline` has passed.
+    pub fn solve_within(
+        &mut self,
+        board: &Board,
+        max_nodes: Option<u64>,
+        deadline: Option<Instant>,
+    ) -> Option<Solution> {
+        self.nodes = 0;
+        self.node_limit = match (self.max_nodes, max_nodes) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.deadline = deadline;
+        self.aborted = false;
+        let score = self.negamax(board, -INFINITY, INFINITY);
+        if self.aborted {
+            return None;
+        }
+        let best_move = if board.game_over() { None } else { self.table[&table_key(board)].mv };
+        Some(Solution { score, best_move, nodes: self.nodes })
+    }
+
+    /// The number of nodes searched by the last call to `solve`, whether or not it finished.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    fn negamax(&mut self, board: &Board, mut alpha: i32, mut beta: i32) -> i32 {
+        self.nodes += 1;
+        if board.game_over() {
+            return disc_difference(board);
+        }
+        if self.node_limit.is_some_and(|n| self.nodes >= n)
+            || (self.nodes.is_multiple_of(1024) && self.deadline.is_some_and(|d| Instant::now() >= d))
+        {
+            self.aborted = true;
+            return 0;
+        }
+
+        let key = table_key(board);
+        let entry = self.table.get(&key).copied();
+        if let Some(entry) = entry {
+            if entry.lower >= beta || entry.lower == entry.upper {
+                return entry.lower;
+            }
+            if entry.upper <= alpha {
+                return entry.upper;
+            }
+            alpha = alpha.max(entry.lower);
+            beta = beta.min(entry.upper);
+        }
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for mv in ordered_moves(board, entry.and_then(|e| e.mv)) {
+            let mut child = *board;
+            child.make_move(mv);
+            let score = -self.negamax(&child, -beta, -alpha);
+            if self.aborted {
+                return 0;
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let mut entry = entry.unwrap_or(Entry { lower: -INFINITY, upper: INFINITY, mv: None });
+        if best_score > original_alpha {
+            entry.lower = best_score;
+            entry.mv = best_move;
+        }
+        if best_score < beta {
+            entry.upper = best_score;
+        }
+        if self.table.len() >= self.max_entries && !self.table.contains_key(&key) {
+            self.table.clear();
+        }
+        self.table.insert(key, entry);
+        best_score
+    }
+}
+
+/// The position key, extended with the halfmove clock since the 50-move rule can change the
+/// result. With the clock included, no position can repeat within a game.
+pub(crate) fn table_key(board: &Board) -> u64 {
+    board.key() ^ u64::from(board.halfmove).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+/// The moves of `board`, with `first` first and then by how many pieces they gain.
+fn ordered_moves(board: &Board, first: Option<Move>) -> Vec<Move> {
+    let them = match board.turn() {
+        Player::White => board.black,
+        Player::Black => board.white,
+    };
+    let mut moves = Vec::new();
+    board.generate_moves(|mv| {
+        let gain = match mv {
+            Move::Pass => 0,
+            Move::Single { to } => 1 + (expand(to.as_set()) & them).count_ones() as i32,
+            Move::Double { to, .. } => (expand(to.as_set()) & them).count_ones() as i32,
+        };
+        let order = if Some(mv) == first { i32::MAX } else { gain };
+        moves.push((mv, order));
+        false
+    });
+    moves.sort_by_key(|&(_, order)| std::cmp::Reverse(order));
+    moves.into_iter().map(|(mv, _)| mv).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{disc_difference, search_score, Solver};
+    use crate::{
+        rng::Rng,
+        search::{Limits, Search, MAX_DEPTH, SCORE_SOLVED_BOUND, SCORE_WIN_BOUND},
+        Board, Move,
+    };
+
+    /// Plain minimax, for positions whose games can only last a few more plies.
+    fn minimax(board: &Board) -> i32 {
+        if board.game_over() {
+            return disc_difference(board);
+        }
+        let mut best = i32::MIN;
+        board.generate_moves(|mv| {
+            let mut child = *board;
+            child.make_move(mv);
+            best = best.max(-minimax(&child));
+            false
+        });
+        best
+    }
+
+    #[test]
+    fn solves_passes() {
+        // white cannot reach the last square, so passes, and black fills it.
+        let board: Board = "xxxxxxx/xxxxxxx/xxxxxxx/ooooooo/ooooooo/ooooooo/oooooo1 x 0 1".parse().unwrap();
+        let mut solver = Solver::new(4);
+        assert!(solver.applies(&board));
+        let solution = solver.solve(&board).unwrap();
+        assert_eq!((solution.score, solution.best_move), (-7, Some(Move::Pass)));
+
+        let mut search = Search::new(1);
+        search.endgame = Some(solver);
+        let result = search.search(&board, Limits::depth(1));
+        assert_eq!((result.score, result.best_move), (search_score(-7), Some(Move::Pass)));
+        // solved scores are never read as mate distances.
+        assert!(search_score(49) < SCORE_WIN_BOUND && search_score(1) >= SCORE_SOLVED_BOUND);
+    }
+
+    #[test]
+    fn search_limits_bound_the_solver() {
+        let board = Board::default();
+        let mut search = Search::new(1);
+        search.endgame = Some(Solver::new(49));
+        let result = search.search(&board, Limits::nodes(2000));
+        assert!(result.depth < MAX_DEPTH && result.best_move.is_some());
+
+        let start = Instant::now();
+        let result = search.search(&board, Limits::time(Duration::from_millis(50)));
+        assert!(result.depth < MAX_DEPTH && start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn agrees_with_minimax_near_the_move_limit() {
+        let mut rng = Rng::new(3);
+        let mut solver = Solver::new(1);
+        let mut bounded = Solver { max_entries: 3, ..Solver::new(1) };
+        let mut solved = 0;
+        while solved < 20 {
+            let mut board = Board::default();
+            while !board.game_over() && board.empty_count() > 1 {
+                board.make_random_move(|lo, hi| rng.range(lo, hi));
+            }
+            if board.game_over() {
+                continue;
+            }
+            // with one empty square and three doubles left before the 50-move rule, every
+            // game ends within four plies.
+            let fen = board.fen();
+            let parts: Vec<&str> = fen.split(' ').collect();
+            let board: Board = format!("{} {} 97 {}", parts[0], parts[1], parts[3]).parse().unwrap();
+            let solution = solver.solve(&board).unwrap();
+            assert_eq!(solution.score, minimax(&board), "{}", board.fen());
+            assert_eq!(bounded.solve(&board).unwrap().score, solution.score, "{}", board.fen());
+            assert!(bounded.table.len() <= 3);
+
+            let mut after = board;
+            after.make_move(solution.best_move.unwrap());
+            assert_eq!(-minimax(&after), solution.score, "{}", board.fen());
+            solved += 1;
+        }
+
+        solver.max_nodes = Some(10);
+        let board: Board = "x5o/7/7/7/7/7/o5x x 0 1".parse().unwrap();
+        assert_eq!(solver.solve(&board), None);
+    }
+}
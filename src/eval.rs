@@ -0,0 +1,111 @@
+use crate::{expand, Board, Player, BB_ALL};
+
+/// The number of classes that the 49 squares fall into under the symmetries of the board.
+pub const NUM_SQUARE_CLASSES: usize = 10;
+/// The number of weights in an [`Evaluator`].
+pub const NUM_WEIGHTS: usize = NUM_SQUARE_CLASSES + 1;
+/// The index of the weight for empty squares next to a side's pieces.
+pub const EXPOSURE: usize = NUM_SQUARE_CLASSES;
+
+/// The weights of the default evaluator: a value for a piece on each class of square, then
+/// the exposure weight.
+pub const DEFAULT_WEIGHTS: [i32; NUM_WEIGHTS] = [112, 104, 98, 103, 99, 97, 101, 99, 98, 100, -4];
+
+/// Short descriptions of the weights, in order.
+pub const WEIGHT_NAMES: [&str; NUM_WEIGHTS] = [
+    "piece on a1", "piece on b1", "piece on b2", "piece on c1", "piece on c2",
+    "piece on c3", "piece on d1", "piece on d2", "piece on d3", "piece on d4",
+    "empty square next to a piece",
+];
+
+/// Maps every square to its symmetry class, numbered from the corner inwards.
+static SQUARE_CLASS: [u8; 64] = {
+    let mut table = [0; 64];
+    let mut sq = 0;
+    while sq < 64 {
+        let (file, rank) = (sq % 8, sq / 8);
+        if file < 7 && rank < 7 {
+            let f = if file < 6 - file { file } else { 6 - file };
+            let r = if rank < 6 - rank { rank } else { 6 - rank };
+            let (lo, hi) = if f < r { (f, r) } else { (r, f) };
+            table[sq] = (hi * (hi + 1) / 2 + lo) as u8;
+        }
+        sq += 1;
+    }
+    table
+};
+
+/// A linear evaluation over a small set of features, from the side to move's perspective.
+///
+/// Scores are in centi-pieces: a weight of 100 on every square class values each piece at 100.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Evaluator {
+    pub weights: [i32; NUM_WEIGHTS],
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self { weights: DEFAULT_WEIGHTS }
+    }
+}
+
+impl Evaluator {
+    pub fn new(weights: [i32; NUM_WEIGHTS]) -> Self {
+        Self { weights }
+    }
+
+    /// Computes the feature vector of `board`, each entry being the side to move's count
+    /// minus the opponent's, so that the evaluation is the dot product with the weights.
+    pub fn features(board: &Board) -> [i32; NUM_WEIGHTS] {
+        let (us, them) = match board.turn() {
+            Player::White => (board.white, board.black),
+            Player::Black => (board.black, board.white),
+        };
+
+        let mut features = [0; NUM_WEIGHTS];
+        for (bb, sign) in [(us, 1), (them, -1)] {
+            let mut bb = bb & BB_ALL;
+            while bb != 0 {
+                let sq = bb.trailing_zeros() as usize;
+                bb &= bb - 1;
+                features[SQUARE_CLASS[sq] as usize] += sign;
+            }
+        }
+
+        let empty = !(us | them | board.walls) & BB_ALL;
+        features[EXPOSURE] = (expand(us) & empty).count_ones() as i32 - (expand(them) & empty).count_ones() as i32;
+
+        features
+    }
+
+    pub fn evaluate(&self, board: &Board) -> i32 {
+        Self::features(board).iter().zip(&self.weights).map(|(f, w)| f * w).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Evaluator, SQUARE_CLASS};
+    use crate::{Board, Square};
+
+    #[test]
+    fn square_classes_are_symmetric() {
+        for sq in Square::all() {
+            let class = SQUARE_CLASS[sq.index()];
+            let mirrored = Square::from_rank_file(sq.file(), 6 - sq.rank());
+            assert_eq!(class, SQUARE_CLASS[mirrored.index()]);
+            assert_eq!(class, SQUARE_CLASS[Square::from_rank_file(sq.rank(), 6 - sq.file()).index()]);
+        }
+        assert_eq!(SQUARE_CLASS[Square::A1.index()], 0);
+        assert_eq!(SQUARE_CLASS[Square::D4.index()], 9);
+    }
+
+    #[test]
+    fn startpos_is_balanced() {
+        let board = Board::default();
+        assert_eq!(Evaluator::default().evaluate(&board), 0);
+        let mut after = board;
+        after.make_move("a6".parse().unwrap());
+        assert!(Evaluator::default().evaluate(&after) < 0);
+    }
+}
@@ -1,6 +1,65 @@
+//! Ataxx rules, search and tooling.
+//!
+//! The rules (`Board`, `Move`, `Square` and the FEN reader) only need `core`, so the crate
+//! builds with `#![no_std]` when the default `std` feature is turned off. The `alloc` feature
+//! adds FEN output and position ranking, and `std` everything else.
+//!
+//! The C interface is the separate `ataxxgen-ffi` package in `ffi/`, so that users of this
+//! crate do not link its exported symbols.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod dataset;
+#[cfg(feature = "std")]
+pub mod dfpn;
+#[cfg(feature = "std")]
+pub mod endgame;
+#[cfg(feature = "std")]
+pub mod eval;
+#[cfg(feature = "std")]
+pub mod mcts;
+#[cfg(feature = "std")]
+pub mod openings;
 pub mod perft;
-
-use std::{cmp::Ordering, fmt::{self, Display, Formatter}, str::FromStr};
+#[cfg(feature = "std")]
+pub mod pgn;
+#[cfg(feature = "std")]
+pub mod puct;
+#[cfg(feature = "alloc")]
+pub mod ranking;
+#[cfg(feature = "std")]
+pub mod record;
+#[cfg(feature = "std")]
+pub mod rescore;
+#[cfg(feature = "std")]
+pub mod retro;
+#[cfg(feature = "std")]
+pub mod rng;
+#[cfg(feature = "std")]
+pub mod search;
+#[cfg(feature = "std")]
+pub mod spsa;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod tree;
+#[cfg(feature = "std")]
+pub mod tt;
+#[cfg(feature = "std")]
+pub mod tune;
+#[cfg(feature = "std")]
+pub mod uai;
+pub mod walk;
+pub mod walls;
+
+use core::{cmp::Ordering, fmt::{self, Display, Formatter}, str::FromStr};
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
 
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -8,7 +67,7 @@ pub struct Board {
     white: u64,
     black: u64,
     walls: u64,
-    ply: u8,
+    ply: u16,
     halfmove: u8,
 }
 
@@ -51,6 +110,89 @@ impl Move {
     }
 }
 
+/// A move packed into 16 bits, for compact storage.
+///
+/// The low six bits hold the target square and the next six the source square, with
+/// bits 12 and 13 tagging the kind of move. Zero is reserved as the null move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub struct PackedMove(u16);
+
+impl PackedMove {
+    pub const NULL: Self = Self(0);
+    pub const PASS: Self = Self(Self::TAG_PASS);
+
+    const TAG_SINGLE: u16 = 1 << 12;
+    const TAG_DOUBLE: u16 = 2 << 12;
+    const TAG_PASS: u16 = 3 << 12;
+
+    pub const fn new(mv: Move) -> Self {
+        match mv {
+            Move::Single { to } => Self(Self::TAG_SINGLE | to.0 as u16),
+            Move::Double { from, to } => Self(Self::TAG_DOUBLE | (from.0 as u16) << 6 | to.0 as u16),
+            Move::Pass => Self::PASS,
+        }
+    }
+
+    /// The unpacked move, or `None` for the null move or an invalid encoding: squares off the
+    /// 7x7 board, or a double move that is not a jump of two squares.
+    pub const fn unpack(self) -> Option<Move> {
+        let to = Square((self.0 & 0x3F) as u8);
+        let from = Square((self.0 >> 6 & 0x3F) as u8);
+        let to_on_board = BB_ALL >> to.0 & 1 != 0;
+        let from_on_board = BB_ALL >> from.0 & 1 != 0;
+        let file_distance = from.file().abs_diff(to.file());
+        let rank_distance = from.rank().abs_diff(to.rank());
+        let is_jump = file_distance <= 2 && rank_distance <= 2 && (file_distance == 2 || rank_distance == 2);
+        match self.0 >> 12 {
+            1 if from.0 == 0 && to_on_board => Some(Move::Single { to }),
+            2 if from_on_board && to_on_board && is_jump => Some(Move::Double { from, to }),
+            3 if self.0 & 0xFFF == 0 => Some(Move::Pass),
+            _ => None,
+        }
+    }
+
+    pub const fn is_null(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn from_raw(raw: u16) -> Self {
+        Self(raw)
+    }
+
+    pub const fn raw(self) -> u16 {
+        self.0
+    }
+}
+
+impl From<Move> for PackedMove {
+    fn from(mv: Move) -> Self {
+        Self::new(mv)
+    }
+}
+
+impl Display for PackedMove {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.unpack() {
+            Some(mv) => write!(f, "{mv}"),
+            None if self.is_null() => write!(f, "(none)"),
+            None => write!(f, "ILLEGAL: PackedMove({:#06x})", self.0),
+        }
+    }
+}
+
+impl FromStr for PackedMove {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "(none)" {
+            return Ok(Self::NULL);
+        }
+        let packed = Self::new(Move::from_str(s)?);
+        // reject moves that parse but cannot be packed, such as h1 or a1b1.
+        packed.unpack().map(|_| packed).ok_or("invalid packed move")
+    }
+}
+
 static SQUARE_NAMES: [&str; 64] = [
     "a1", "b1", "c1", "d1", "e1", "f1", "g1", "h1", "a2", "b2", "c2", "d2", "e2", "f2", "g2", "h2",
     "a3", "b3", "c3", "d3", "e3", "f3", "g3", "h3", "a4", "b4", "c4", "d4", "e4", "f4", "g4", "h4",
@@ -71,7 +213,7 @@ impl Display for Square {
     }
 }
 
-impl std::fmt::Debug for Square {
+impl fmt::Debug for Square {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let name = SQUARE_NAMES.get(self.index()).copied();
         if let Some(name) = name {
@@ -131,7 +273,7 @@ impl FromStr for Move {
 }
 
 impl Display for Move {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match *self {
             Move::Pass => write!(f, "0000"),
             Move::Single { to } => write!(f, "{}", to),
@@ -183,11 +325,58 @@ const fn shift_left(bb: u64) -> u64 {
 const fn shift_right(bb: u64) -> u64 {
     (bb >> 1) & BB_ALL
 }
+/// The splitmix64 finaliser, a cheap bijective mixing function.
+const fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 const fn expand(bb: u64) -> u64 {
     let vertical = shift_up(bb) | shift_down(bb) | bb;
     (vertical | shift_left(vertical) | shift_right(vertical)) & BB_ALL
 }
 
+/// Mirrors the playing area top-to-bottom.
+const fn flip_vertical(bb: u64) -> u64 {
+    (bb & BB_ALL).swap_bytes() >> 8
+}
+
+/// Mirrors the playing area left-to-right.
+const fn flip_horizontal(bb: u64) -> u64 {
+    (bb & BB_ALL).reverse_bits().swap_bytes() >> 1
+}
+
+/// Mirrors the playing area in the a1-g7 diagonal.
+const fn transpose(bb: u64) -> u64 {
+    const K1: u64 = 0x5500_5500_5500_5500;
+    const K2: u64 = 0x3333_0000_3333_0000;
+    const K4: u64 = 0x0F0F_0F0F_0000_0000;
+    let mut x = bb & BB_ALL;
+    let mut t = K4 & (x ^ (x << 28));
+    x ^= t ^ (t >> 28);
+    t = K2 & (x ^ (x << 14));
+    x ^= t ^ (t >> 14);
+    t = K1 & (x ^ (x << 7));
+    x ^= t ^ (t >> 7);
+    x
+}
+
+/// Applies one of the eight symmetries of the square: bit 2 of `sym` transposes,
+/// then bit 1 flips vertically and bit 0 flips horizontally.
+const fn apply_symmetry(mut bb: u64, sym: u8) -> u64 {
+    if sym & 4 != 0 {
+        bb = transpose(bb);
+    }
+    if sym & 2 != 0 {
+        bb = flip_vertical(bb);
+    }
+    if sym & 1 != 0 {
+        bb = flip_horizontal(bb);
+    }
+    bb & BB_ALL
+}
+
 #[derive(PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
 pub struct Square(u8);
 
@@ -295,7 +484,7 @@ impl Square {
     }
 
     pub fn distance(a: Self, b: Self) -> u8 {
-        std::cmp::max(a.file().abs_diff(b.file()), a.rank().abs_diff(b.rank()))
+        core::cmp::max(a.file().abs_diff(b.file()), a.rank().abs_diff(b.rank()))
     }
 
     pub const fn signed_inner(self) -> i8 {
@@ -413,14 +602,42 @@ impl Board {
         }
     }
 
+    /// The start position with walls on the squares of `walls`. Walls on the starting squares
+    /// and outside the 7x7 board are left out. See the `walls` module for standard layouts.
+    pub fn with_walls(walls: u64) -> Board {
+        Board { walls: walls & BB_ALL & !walls::START_SQUARES | RANK_8 | FILE_H, ..Board::new() }
+    }
+
+    /// The start position of a `size`x`size` board in the a1 corner, with walls on the rest
+    /// of the 7x7 board and pieces in the corners of the smaller one.
+    pub fn small(size: u8) -> Board {
+        assert!((2..=7).contains(&size), "boards are 2x2 to 7x7");
+        let last = size - 1;
+        let area = (0..size)
+            .flat_map(|rank| (0..size).map(move |file| Square::from_rank_file(rank, file).as_set()))
+            .fold(0, |area, sq| area | sq);
+        Board {
+            white: Square::from_rank_file(last, 0).as_set() | Square::from_rank_file(0, last).as_set(),
+            black: Square::A1.as_set() | Square::from_rank_file(last, last).as_set(),
+            walls: BB_ALL & !area | RANK_8 | FILE_H,
+            ply: 0,
+            halfmove: 0,
+        }
+    }
+
     pub fn turn(&self) -> Player {
-        if self.ply % 2 == 0 {
+        if self.ply.is_multiple_of(2) {
             Player::White
         } else {
             Player::Black
         }
     }
 
+    /// The number of the current full move, starting at 1 and incremented after black moves.
+    pub fn fullmove(&self) -> u16 {
+        self.ply / 2 + 1
+    }
+
     pub fn make_move(&mut self, mv: Move) {
         match mv {
             Move::Pass => {}
@@ -458,7 +675,9 @@ impl Board {
                 }
             }
         }
-        self.ply += 1;
+        // Past the last move number the board can hold, the number stops advancing but the
+        // side to move still alternates.
+        self.ply = if self.ply == MAX_PLY { MAX_PLY - 1 } else { self.ply + 1 };
     }
 
     pub fn generate_moves(&self, mut listener: impl FnMut(Move) -> bool) {
@@ -505,6 +724,16 @@ impl Board {
         }
     }
 
+    /// Whether `mv` is one of the moves [`Board::generate_moves`] would generate.
+    pub fn is_legal(&self, mv: Move) -> bool {
+        let mut legal = false;
+        self.generate_moves(|m| {
+            legal = m == mv;
+            legal
+        });
+        legal
+    }
+
     pub fn make_random_move(&mut self, mut rng: impl FnMut(usize, usize) -> usize) {
         if self.game_over() {
             return;
@@ -587,6 +816,11 @@ impl Board {
         }
     }
 
+    /// The number of squares with neither a piece nor a wall.
+    pub fn empty_count(&self) -> u32 {
+        (BB_ALL & !(self.white | self.black | self.walls)).count_ones()
+    }
+
     pub fn player_at(&self, sq: Square) -> Option<Player> {
         if self.white & sq.as_set() != 0 {
             Some(Player::White)
@@ -601,60 +835,96 @@ impl Board {
         self.walls & sq.as_set() != 0
     }
 
+    /// The squares of the 7x7 board that have walls.
+    pub fn walls(&self) -> u64 {
+        self.walls & BB_ALL
+    }
+
+    /// The eight images of this position under the symmetries of the board, starting with the identity.
+    pub fn symmetries(&self) -> [Board; 8] {
+        core::array::from_fn(|sym| Board {
+            white: apply_symmetry(self.white, sym as u8),
+            black: apply_symmetry(self.black, sym as u8),
+            walls: apply_symmetry(self.walls, sym as u8) | RANK_8 | FILE_H,
+            ..*self
+        })
+    }
+
+    /// The smallest `key` among the symmetric images of this position, so that positions
+    /// that are equal up to symmetry share a key.
+    pub fn canonical_key(&self) -> u64 {
+        self.symmetries().iter().map(Board::key).min().unwrap_or_else(|| self.key())
+    }
+
+    /// A 64-bit hash of the pieces, walls and side to move. The move counters are not included.
+    pub fn key(&self) -> u64 {
+        let stm = match self.turn() {
+            Player::White => 0,
+            Player::Black => 0x5555_5555_5555_5555,
+        };
+        mix(self.white ^ mix(self.black ^ mix((self.walls & BB_ALL) ^ stm)))
+    }
+
+    #[cfg(feature = "alloc")]
     pub fn fen(&self) -> String {
-        let mut fen = String::new();
+        let mut fen = String::with_capacity(FEN_MAX_LEN);
+        self.write_fen(&mut fen).expect("writing to a string cannot fail");
+        fen
+    }
 
-        for rank in (0u8..7).rev() {
-            let mut file: u8 = 0;
+    /// The FEN of this position in a buffer on the stack.
+    pub fn fen_buf(&self) -> FenBuf {
+        let mut buf = FenBuf { bytes: [0; FEN_MAX_LEN], len: 0 };
+        self.write_fen(&mut buf).expect("every FEN fits in the buffer");
+        buf
+    }
 
-            while file < 7 {
+    /// Writes the FEN of this position without allocating.
+    pub fn write_fen(&self, out: &mut impl fmt::Write) -> fmt::Result {
+        for rank in (0u8..7).rev() {
+            let mut empty_squares = 0;
+            for file in 0u8..7 {
                 let sq = Square::from_rank_file(rank, file);
-
-                match self.player_at(sq) {
-                    Some(p) => fen.push(p.to_char()),
+                let c = match self.player_at(sq) {
+                    Some(p) => p.to_char(),
+                    None if self.wall_at(sq) => '-',
                     None => {
-                        if self.wall_at(sq) {
-                            fen.push('-');
-                        } else {
-                            let mut empty_squares: u32 = 1;
-
-                            while file < 6
-                                && self.player_at(Square::from_rank_file(rank, file + 1)).is_none()
-                            {
-                                file += 1;
-                                empty_squares += 1;
-                            }
-
-                            fen += empty_squares.to_string().as_str();
-                        }
+                        empty_squares += 1;
+                        continue;
                     }
+                };
+                if empty_squares > 0 {
+                    out.write_char(char::from(b'0' + empty_squares))?;
+                    empty_squares = 0;
                 }
-
-                file += 1;
+                out.write_char(c)?;
+            }
+            if empty_squares > 0 {
+                out.write_char(char::from(b'0' + empty_squares))?;
             }
-
             if rank > 0 {
-                fen.push('/');
+                out.write_char('/')?;
             }
         }
 
-        fen + format!(
-            " {} {} {}",
-            self.turn().to_char(),
-            self.halfmove,
-            self.ply / 2 + 1
-        )
-        .as_str()
+        write!(out, " {} {} {}", self.turn().to_char(), self.halfmove, self.fullmove())
     }
 
-    fn reset_from_fen_parts(&mut self, parts: &[&str]) -> Result<(), FenError> {
-        if parts.len() < 4 {
-            return Err(FenError::NotEnoughParts);
-        }
+    /// Writes the FEN of this position to a byte stream, without allocating.
+    #[cfg(feature = "std")]
+    pub fn write_fen_io(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        out.write_all(self.fen_buf().as_bytes())
+    }
 
-        let ranks: Vec<&str> = parts[0].split('/').collect();
+    pub fn reset_from_fen(&mut self, fen: &str) -> Result<(), FenError> {
+        let mut parts = fen.split_whitespace();
+        let (Some(board), Some(stm), Some(halfmove), Some(fullmove)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(FenError::NotEnoughParts);
+        };
 
-        match ranks.len().cmp(&7) {
+        match board.split('/').count().cmp(&7) {
             Ordering::Less => return Err(FenError::NotEnoughRanks),
             Ordering::Greater => return Err(FenError::TooManyRanks),
             Ordering::Equal => {}
@@ -663,12 +933,12 @@ impl Board {
         let mut state = Self {
             white: 0,
             black: 0,
-            walls: 0,
+            walls: RANK_8 | FILE_H,
             ply: 0,
             halfmove: 0,
         };
 
-        for (rank_idx, rank) in ranks.iter().enumerate() {
+        for (rank_idx, rank) in board.split('/').enumerate() {
             let mut file_idx: u8 = 0;
 
             for c in rank.chars() {
@@ -703,40 +973,34 @@ impl Board {
             }
         }
 
-        if parts[1].len() != 1 {
+        if stm.len() != 1 {
             return Err(FenError::InvalidStm);
         }
 
-        let black_to_move = if let Some(stm) = Player::from_char(parts[1].chars().nth(0).unwrap()) {
+        let black_to_move = if let Some(stm) = Player::from_char(stm.chars().nth(0).unwrap()) {
             stm == Player::Black
         } else {
             return Err(FenError::InvalidStm);
         };
 
-        if let Ok(halfmove) = parts[2].parse::<u8>() {
+        if let Ok(halfmove) = halfmove.parse::<u8>() {
             state.halfmove = halfmove;
         } else {
             return Err(FenError::InvalidHalfmove);
         }
 
-        let fullmove = if let Ok(fullmove) = parts[3].parse::<u32>() {
-            fullmove
-        } else {
-            return Err(FenError::InvalidFullmove);
+        let fullmove = match fullmove.parse::<u16>() {
+            Ok(fullmove @ 1..=MAX_FULLMOVE) => fullmove,
+            _ => return Err(FenError::InvalidFullmove),
         };
 
-        state.ply = ((fullmove - 1) * 2 + if black_to_move { 1 } else { 0 }) as u8;
+        state.ply = (fullmove - 1) * 2 + u16::from(black_to_move);
 
         *self = state;
 
         Ok(())
     }
 
-    pub fn reset_from_fen(&mut self, fen: &str) -> Result<(), FenError> {
-        let parts: Vec<&str> = fen.split_whitespace().collect();
-        self.reset_from_fen_parts(parts.as_slice())
-    }
-
     pub fn feature_map(&self, mut listener: impl FnMut(usize)) {
         const OFFSET: usize = 7 * 7;
         let (mut us, mut them) = match self.turn() {
@@ -773,7 +1037,7 @@ impl FromStr for Board {
 }
 
 impl Display for Board {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         const BLD: &str = "\x1b[1m";
         const RED: &str = "\x1b[31m";
         const BLU: &str = "\x1b[34m";
@@ -788,19 +1052,15 @@ impl Display for Board {
 
             for file in 0u8..7 {
                 let sq = Square::from_rank_file(rank, file);
-                write!(
-                    f,
-                    " │ {}",
-                    if self.wall_at(sq) {
-                        "-".into()
-                    } else {
-                        match self.player_at(sq) {
-                            Some(Player::White) => format!("{BLD}{RED}X{RST}"),
-                            Some(Player::Black) => format!("{BLD}{BLU}O{RST}"),
-                            None => " ".into(),
-                        }
+                if self.wall_at(sq) {
+                    write!(f, " │ -")?;
+                } else {
+                    match self.player_at(sq) {
+                        Some(Player::White) => write!(f, " │ {BLD}{RED}X{RST}")?,
+                        Some(Player::Black) => write!(f, " │ {BLD}{BLU}O{RST}")?,
+                        None => write!(f, " │  ")?,
                     }
-                )?;
+                }
             }
 
             writeln!(f, " │ {}", rank + 1)?;
@@ -811,15 +1071,69 @@ impl Display for Board {
         writeln!(f, "   a   b   c   d   e   f   g")?;
         writeln!(f)?;
 
-        write!(
-            f,
-            "{} to move",
-            if self.turn() == Player::White {
-                format!("{BLD}{RED}Red{RST} [X]")
-            } else {
-                format!("{BLD}{BLU}Blue{RST} [O]")
-            }
-        )
+        if self.turn() == Player::White {
+            write!(f, "{BLD}{RED}Red{RST} [X] to move")
+        } else {
+            write!(f, "{BLD}{BLU}Blue{RST} [O] to move")
+        }
+    }
+}
+
+/// The largest move number a board can hold. Move numbers start at 1, and the ply after
+/// black's move at this number must still fit in 16 bits.
+pub const MAX_FULLMOVE: u16 = 32767;
+
+/// The ply of black's move at [`MAX_FULLMOVE`], the last one a board can reach.
+const MAX_PLY: u16 = (MAX_FULLMOVE - 1) * 2 + 1;
+
+/// The longest FEN: seven ranks of seven squares, six slashes, the side to move, a
+/// three-digit halfmove clock and a five-digit move number, with the spaces between them.
+pub const FEN_MAX_LEN: usize = 7 * 7 + 6 + 2 + 4 + 6;
+
+/// A FEN held on the stack, made by [`Board::fen_buf`].
+#[derive(Clone, Copy)]
+pub struct FenBuf {
+    bytes: [u8; FEN_MAX_LEN],
+    len: usize,
+}
+
+impl FenBuf {
+    pub fn as_str(&self) -> &str {
+        // only whole strings are ever written, so the bytes are valid UTF-8.
+        core::str::from_utf8(&self.bytes[..self.len]).expect("FENs are ASCII")
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl fmt::Write for FenBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.bytes.get_mut(self.len..end).ok_or(fmt::Error)?.copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl core::ops::Deref for FenBuf {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Display for FenBuf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for FenBuf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
     }
 }
 
@@ -837,7 +1151,7 @@ pub enum FenError {
 }
 
 impl Display for FenError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             FenError::NotEnoughParts => write!(f, "Incomplete FEN"),
             FenError::NotEnoughRanks => write!(f, "Not enough ranks in FEN"),
@@ -852,6 +1166,9 @@ impl Display for FenError {
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for FenError {}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -908,4 +1225,108 @@ mod tests {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn packed_move_roundtrip() {
+        use super::{Move, PackedMove, Square};
+        let mut moves = vec![Move::Pass];
+        for to in Square::all() {
+            moves.push(Move::Single { to });
+            for from in Square::all().filter(|&from| Square::distance(from, to) == 2) {
+                moves.push(Move::Double { from, to });
+            }
+        }
+        let mut seen = std::collections::HashSet::new();
+        for mv in moves {
+            let packed = PackedMove::from(mv);
+            assert!(!packed.is_null());
+            assert!(seen.insert(packed), "{mv} collides with another move");
+            assert_eq!(packed.unpack(), Some(mv));
+            assert_eq!(packed.to_string(), mv.to_string());
+            assert_eq!(packed.to_string().parse::<PackedMove>(), Ok(packed));
+        }
+        assert_eq!(PackedMove::NULL.unpack(), None);
+        assert_eq!(PackedMove::PASS.unpack(), Some(Move::Pass));
+        assert_eq!(PackedMove::NULL.to_string().parse::<PackedMove>(), Ok(PackedMove::NULL));
+
+        let h1 = Square::from_rank_file(0, 7);
+        let a8 = Square::from_rank_file(7, 0);
+        let a1 = Square::from_rank_file(0, 0);
+        let c3 = Square::from_rank_file(2, 2);
+        let b2 = Square::from_rank_file(1, 1);
+        let a4 = Square::from_rank_file(3, 0);
+        assert_eq!(PackedMove::new(Move::Single { to: h1 }).unpack(), None);
+        assert_eq!(PackedMove::new(Move::Single { to: a8 }).unpack(), None);
+        assert_eq!(PackedMove::new(Move::Double { from: a1, to: h1 }).unpack(), None);
+        assert_eq!(PackedMove::new(Move::Double { from: a1, to: b2 }).unpack(), None);
+        assert_eq!(PackedMove::new(Move::Double { from: a1, to: a4 }).unpack(), None);
+        let jump = Move::Double { from: a1, to: c3 };
+        assert_eq!(PackedMove::new(jump).unpack(), Some(jump));
+        for name in ["h1", "a8c8", "a1a1", "a1b1"] {
+            assert!(name.parse::<PackedMove>().is_err(), "{name} should not parse");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn fen_roundtrip() {
+        use super::Board;
+        let default = Board::default();
+        assert_eq!(default.fen().parse::<Board>().unwrap(), default);
+        let fen = "x2-2o/7/2-1-2/3-3/2-1-2/7/o2-2x o 3 12";
+        let board: Board = fen.parse().unwrap();
+        assert_eq!(board.fen(), fen);
+        assert!("x5o/7/7/7/7/7/o5x x 0 0".parse::<Board>().is_err());
+        assert!("x5o/7/7/7/7/7/o5x x 0 40000".parse::<Board>().is_err());
+        assert!("x5o/7/7/7/7/7/o5x o 0 32768".parse::<Board>().is_err());
+    }
+
+    #[test]
+    fn moves_past_move_limit() {
+        use super::{Board, Move, Player, Square, MAX_FULLMOVE};
+        let mut board: Board = "x5o/7/7/7/7/7/o5x o 0 32767".parse().unwrap();
+        assert_eq!(board.fullmove(), MAX_FULLMOVE);
+        board.make_move(Move::Single { to: Square::B2 });
+        assert_eq!((board.turn(), board.fullmove()), (Player::White, MAX_FULLMOVE));
+        board.make_move(Move::Single { to: Square::B6 });
+        assert_eq!((board.turn(), board.fullmove()), (Player::Black, MAX_FULLMOVE));
+        board.make_move(Move::Single { to: Square::F6 });
+        assert_eq!((board.turn(), board.fullmove()), (Player::White, MAX_FULLMOVE));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn fen_writers_agree() {
+        use super::{rng::Rng, Board, FEN_MAX_LEN};
+        let mut rng = Rng::new(1);
+        let mut board: Board = "x2-2o/7/2-1-2/3-3/2-1-2/7/o2-2x x 0 1".parse().unwrap();
+        while !board.game_over() {
+            let mut bytes = Vec::new();
+            board.write_fen_io(&mut bytes).unwrap();
+            assert_eq!(board.fen_buf().as_str(), board.fen());
+            assert_eq!(bytes, board.fen().as_bytes());
+            board.make_random_move(|lo, hi| rng.range(lo, hi));
+        }
+
+        let longest = "xoxoxox/oxoxoxo/xoxoxox/oxoxoxo/xoxoxox/oxoxoxo/xoxoxox o 255 32767";
+        let board: Board = longest.parse().unwrap();
+        assert_eq!(board.fen_buf().len(), FEN_MAX_LEN);
+        assert_eq!(board.fen_buf().as_str(), longest);
+    }
+
+    #[test]
+    fn symmetries_preserve_perft() {
+        use super::{perft::perft, Board, Square};
+        let board: Board = "x2-1o1/7/1-5/3x3/7/2o4/o5x o 0 1".parse().unwrap();
+        let images = board.symmetries();
+        assert_eq!(images[0], board);
+        for image in images {
+            assert_eq!(perft(&image, 3), perft(&board, 3));
+            assert_eq!(image.canonical_key(), board.canonical_key());
+            assert_eq!(image.symmetries().iter().filter(|&&b| b == board).count(), 1);
+        }
+        let flipped = images[2];
+        assert_eq!(flipped.player_at(Square::A7), Some(super::Player::Black));
+        assert!(flipped.wall_at(Square::D1) && flipped.wall_at(Square::B3));
+    }
+}
@@ -0,0 +1,349 @@
+use std::time::{Duration, Instant};
+
+use crate::{rng::Rng, Board, Move, Player};
+
+pub(crate) const NO_NODE: u32 = u32::MAX;
+
+/// Conditions under which a search stops. Any limit that is hit ends the search.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of visits to the root.
+    pub visits: Option<u64>,
+    /// Maximum number of nodes in the tree.
+    pub nodes: Option<usize>,
+    /// Maximum wall-clock time.
+    pub time: Option<Duration>,
+}
+
+impl Limits {
+    pub fn visits(visits: u64) -> Self {
+        Self { visits: Some(visits), ..Self::default() }
+    }
+
+    pub fn nodes(nodes: usize) -> Self {
+        Self { nodes: Some(nodes), ..Self::default() }
+    }
+
+    pub fn time(time: Duration) -> Self {
+        Self { time: Some(time), ..Self::default() }
+    }
+
+    /// Whether no limit is set, so a search would never stop.
+    pub fn is_unbounded(&self) -> bool {
+        self.visits.is_none() && self.nodes.is_none() && self.time.is_none()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Params {
+    /// The UCT exploration constant.
+    pub exploration: f64,
+    /// The number of random playouts run from each newly expanded leaf.
+    pub playouts_per_leaf: u32,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self { exploration: std::f64::consts::SQRT_2, playouts_per_leaf: 1 }
+    }
+}
+
+/// A node of a tree stored in one arena, with the children of each node contiguous.
+pub(crate) trait ArenaNode: Copy {
+    /// The index of the first child, or `NO_NODE` before expansion.
+    fn first_child(&self) -> u32;
+
+    fn set_first_child(&mut self, first_child: u32);
+
+    fn child_count(&self) -> u32;
+
+    fn children(&self) -> std::ops::Range<usize> {
+        match self.first_child() {
+            NO_NODE => 0..0,
+            first => first as usize..(first + self.child_count()) as usize,
+        }
+    }
+}
+
+/// Copies the subtree below `root` into a fresh arena with `root` first, breadth-first so
+/// that siblings stay contiguous.
+pub(crate) fn reroot<N: ArenaNode>(nodes: &[N], root: usize) -> Vec<N> {
+    let mut rerooted = Vec::with_capacity(nodes.len());
+    rerooted.push(nodes[root]);
+    let mut next = 0;
+    while next < rerooted.len() {
+        let old_children = rerooted[next].children();
+        if !old_children.is_empty() {
+            let first_child = rerooted.len() as u32;
+            rerooted[next].set_first_child(first_child);
+            rerooted.extend_from_slice(&nodes[old_children]);
+        }
+        next += 1;
+    }
+    rerooted
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    mv: Move,
+    first_child: u32,
+    child_count: u32,
+    expanded: bool,
+    visits: u64,
+    /// Sum of rewards (1 win, 0.5 draw, 0 loss) from the perspective of the player who played `mv`.
+    reward: f64,
+}
+
+impl Node {
+    const fn new(mv: Move) -> Self {
+        Self { mv, first_child: NO_NODE, child_count: 0, expanded: false, visits: 0, reward: 0.0 }
+    }
+}
+
+impl ArenaNode for Node {
+    fn first_child(&self) -> u32 {
+        self.first_child
+    }
+
+    fn set_first_child(&mut self, first_child: u32) {
+        self.first_child = first_child;
+    }
+
+    fn child_count(&self) -> u32 {
+        self.child_count
+    }
+}
+
+/// A UCT Monte Carlo tree search that evaluates leaves with uniformly random playouts.
+pub struct Mcts {
+    board: Board,
+    nodes: Vec<Node>,
+    params: Params,
+    rng: Rng,
+}
+
+impl Mcts {
+    pub fn new(board: Board, params: Params, rng: Rng) -> Self {
+        Self { board, nodes: vec![Node::new(Move::Pass)], params, rng }
+    }
+
+    /// The position at the root of the tree.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The number of nodes currently in the tree.
+    pub fn tree_size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The number of visits the root has received.
+    pub fn root_visits(&self) -> u64 {
+        self.nodes[0].visits
+    }
+
+    /// Runs the search until one of `limits` is hit, returning the best move,
+    /// or `None` if the root position is terminal.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limits` sets no limit.
+    pub fn search(&mut self, limits: Limits) -> Option<Move> {
+        assert!(!limits.is_unbounded(), "MCTS needs at least one limit");
+        if self.board.game_over() {
+            return None;
+        }
+
+        let start = Instant::now();
+        let mut path = Vec::new();
+        loop {
+            if limits.visits.is_some_and(|v| self.root_visits() >= v)
+                || limits.nodes.is_some_and(|n| self.tree_size() >= n)
+                || limits.time.is_some_and(|t| start.elapsed() >= t)
+            {
+                break;
+            }
+            self.iterate(&mut path);
+        }
+
+        self.best_move()
+    }
+
+    fn iterate(&mut self, path: &mut Vec<(usize, Player)>) {
+        path.clear();
+        let mut board = self.board;
+        let mut node = 0;
+
+        // selection
+        while self.nodes[node].expanded && self.nodes[node].child_count > 0 {
+            let child = self.select_child(node);
+            path.push((child, board.turn()));
+            board.make_move(self.nodes[child].mv);
+            node = child;
+        }
+
+        // expansion
+        if !board.game_over() {
+            self.expand(node, &board);
+            if let Some(child) = self.nodes[node].children().next() {
+                path.push((child, board.turn()));
+                board.make_move(self.nodes[child].mv);
+            }
+        }
+
+        // simulation
+        let mut white_reward = 0.0;
+        let playouts = self.params.playouts_per_leaf.max(1);
+        for _ in 0..playouts {
+            let mut rollout = board;
+            while !rollout.game_over() {
+                rollout.make_random_move(|lo, hi| self.rng.range(lo, hi));
+            }
+            white_reward += match rollout.outcome() {
+                Some(Some(Player::White)) => 1.0,
+                Some(Some(Player::Black)) => 0.0,
+                _ => 0.5,
+            };
+        }
+
+        // backpropagation
+        let playouts = u64::from(playouts);
+        self.nodes[0].visits += playouts;
+        for &(idx, mover) in path.iter() {
+            let node = &mut self.nodes[idx];
+            node.visits += playouts;
+            node.reward += match mover {
+                Player::White => white_reward,
+                Player::Black => playouts as f64 - white_reward,
+            };
+        }
+    }
+
+    fn select_child(&self, parent: usize) -> usize {
+        let parent_node = &self.nodes[parent];
+        let log_n = (parent_node.visits.max(1) as f64).ln();
+        let mut best = parent_node.first_child as usize;
+        let mut best_score = f64::NEG_INFINITY;
+        for idx in parent_node.children() {
+            let child = &self.nodes[idx];
+            if child.visits == 0 {
+                return idx;
+            }
+            let n = child.visits as f64;
+            let score = child.reward / n + self.params.exploration * (log_n / n).sqrt();
+            if score > best_score {
+                best_score = score;
+                best = idx;
+            }
+        }
+        best
+    }
+
+    fn expand(&mut self, node: usize, board: &Board) {
+        let first_child = self.nodes.len() as u32;
+        let nodes = &mut self.nodes;
+        board.generate_moves(|mv| {
+            nodes.push(Node::new(mv));
+            false
+        });
+        let child_count = self.nodes.len() as u32 - first_child;
+        let node = &mut self.nodes[node];
+        node.first_child = first_child;
+        node.child_count = child_count;
+        node.expanded = true;
+    }
+
+    /// The most visited move at the root.
+    pub fn best_move(&self) -> Option<Move> {
+        self.nodes[0]
+            .children()
+            .max_by_key(|&idx| self.nodes[idx].visits)
+            .map(|idx| self.nodes[idx].mv)
+    }
+
+    /// The expected reward (0 to 1) of the root position for the side to move.
+    pub fn root_value(&self) -> f64 {
+        let (visits, reward) = self.nodes[0]
+            .children()
+            .map(|idx| &self.nodes[idx])
+            .fold((0, 0.0), |(v, r), child| (v + child.visits, r + child.reward));
+        if visits == 0 {
+            0.5
+        } else {
+            reward / visits as f64
+        }
+    }
+
+    /// The root visit distribution: every legal root move paired with the fraction of visits it received.
+    pub fn root_policy(&self) -> Vec<(Move, f64)> {
+        let children = self.nodes[0].children();
+        let total = children.clone().map(|idx| self.nodes[idx].visits).sum::<u64>().max(1) as f64;
+        children.map(|idx| (self.nodes[idx].mv, self.nodes[idx].visits as f64 / total)).collect()
+    }
+
+    /// Plays `mv` at the root, keeping the subtree below it for the next search.
+    pub fn advance(&mut self, mv: Move) {
+        self.board.make_move(mv);
+        let new_root = self.nodes[0].children().find(|&idx| self.nodes[idx].mv == mv);
+
+        let Some(new_root) = new_root else {
+            self.nodes.clear();
+            self.nodes.push(Node::new(Move::Pass));
+            return;
+        };
+
+        self.nodes = reroot(&self.nodes, new_root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Limits, Mcts, Params};
+    use crate::{rng::Rng, Board, Player};
+
+    #[test]
+    #[should_panic(expected = "at least one limit")]
+    fn rejects_unbounded_search() {
+        Mcts::new(Board::default(), Params::default(), Rng::new(1)).search(Limits::default());
+    }
+
+    #[test]
+    fn root_policy_is_a_distribution() {
+        let mut mcts = Mcts::new(Board::default(), Params::default(), Rng::new(1));
+        let best = mcts.search(Limits::visits(500)).unwrap();
+        let policy = mcts.root_policy();
+        let mut legal = 0;
+        Board::default().generate_moves(|_| {
+            legal += 1;
+            false
+        });
+        assert_eq!(policy.len(), legal);
+        assert!(policy.iter().any(|&(mv, _)| mv == best));
+        let total: f64 = policy.iter().map(|&(_, p)| p).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn finds_immediate_win() {
+        let board: Board = "7/7/7/7/7/1o5/x6 x 0 1".parse().unwrap();
+        let mut mcts = Mcts::new(board, Params::default(), Rng::new(2));
+        let best = mcts.search(Limits::visits(2000)).unwrap();
+        let mut after = board;
+        after.make_move(best);
+        assert_eq!(after.outcome(), Some(Some(Player::White)));
+    }
+
+    #[test]
+    fn advance_keeps_subtree() {
+        let mut mcts = Mcts::new(Board::default(), Params::default(), Rng::new(3));
+        let best = mcts.search(Limits::visits(1000)).unwrap();
+        let policy = mcts.root_policy();
+        let (_, share) = policy.into_iter().find(|&(mv, _)| mv == best).unwrap();
+        mcts.advance(best);
+        let mut expected = Board::default();
+        expected.make_move(best);
+        assert_eq!(*mcts.board(), expected);
+        assert_eq!(mcts.root_visits(), (share * 1000.0).round() as u64);
+        assert!(mcts.tree_size() > 1);
+    }
+}
@@ -0,0 +1,403 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+use crate::{
+    dataset::Dedup,
+    rng::Rng,
+    search::{for_each_split, Limits, Search},
+    Board, FenError, Move,
+};
+
+/// How many random lines `sample` tries per opening asked for before giving up on finding
+/// new positions.
+const SAMPLE_ATTEMPTS: usize = 20;
+
+/// How many random lines [`random_opening`] tries before giving up on finding one that is
+/// still in play.
+pub const OPENING_ATTEMPTS: u32 = 1000;
+
+/// A line of moves from a start position, with the position it leads to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Opening {
+    pub root: Board,
+    pub moves: Vec<Move>,
+    pub board: Board,
+    /// The score of `board` from a short search, from the side to move's perspective.
+    pub score: Option<i32>,
+}
+
+impl Opening {
+    pub fn new(root: Board) -> Self {
+        Self { root, moves: Vec::new(), board: root, score: None }
+    }
+
+    fn extend(&self, mv: Move) -> Self {
+        let mut next = self.clone();
+        next.moves.push(mv);
+        next.board.make_move(mv);
+        next
+    }
+}
+
+/// Filters out openings whose position has been seen before.
+struct Seen {
+    dedup: Dedup,
+    keys: HashSet<u64>,
+}
+
+impl Seen {
+    fn new(dedup: Dedup) -> Self {
+        Self { dedup, keys: HashSet::new() }
+    }
+
+    /// Returns true the first time a position is seen.
+    fn insert(&mut self, board: &Board) -> bool {
+        match self.dedup {
+            Dedup::Off => true,
+            Dedup::Position => self.keys.insert(board.key()),
+            Dedup::Symmetric => self.keys.insert(board.canonical_key()),
+        }
+    }
+}
+
+/// Every line of `plies` moves from each of `roots`, skipping lines that end the game.
+///
+/// Lines are extended one ply at a time, and each ply only keeps the first line to reach a
+/// position, so transpositions are expanded once.
+pub fn enumerate(roots: &[Board], plies: u32, dedup: Dedup) -> Vec<Opening> {
+    let mut seen = Seen::new(dedup);
+    let mut layer: Vec<Opening> =
+        roots.iter().filter(|root| seen.insert(root)).map(|&root| Opening::new(root)).collect();
+    for _ in 0..plies {
+        let mut seen = Seen::new(dedup);
+        let mut next = Vec::new();
+        for opening in &layer {
+            opening.board.generate_moves(|mv| {
+                let extended = opening.extend(mv);
+                if !extended.board.game_over() && seen.insert(&extended.board) {
+                    next.push(extended);
+                }
+                false
+            });
+        }
+        layer = next;
+    }
+    layer
+}
+
+/// A position `plies` random moves from the start position that is still in play, or `None`
+/// if every one of [`OPENING_ATTEMPTS`] tries ended the game.
+pub fn random_opening(plies: u32, rng: &mut Rng) -> Option<Board> {
+    (0..OPENING_ATTEMPTS).find_map(|_| {
+        let mut board = Board::default();
+        for _ in 0..plies {
+            board.make_random_move(|lo, hi| rng.range(lo, hi));
+        }
+        (!board.game_over()).then_some(board)
+    })
+}
+
+/// Up to `count` openings made of `plies` random moves from a random one of `roots`.
+/// Fewer are returned if new positions become too hard to find.
+pub fn sample(roots: &[Board], plies: u32, count: usize, dedup: Dedup, rng: &mut Rng) -> Vec<Opening> {
+    let mut seen = Seen::new(dedup);
+    let mut openings = Vec::new();
+    if roots.is_empty() {
+        return openings;
+    }
+    for _ in 0..count.saturating_mul(SAMPLE_ATTEMPTS) {
+        if openings.len() == count {
+            break;
+        }
+        let mut opening = Opening::new(roots[rng.range(0, roots.len())]);
+        for _ in 0..plies {
+            if opening.board.game_over() {
+                break;
+            }
+            let mut moves = Vec::new();
+            opening.board.generate_moves(|mv| {
+                moves.push(mv);
+                false
+            });
+            opening = opening.extend(moves[rng.range(0, moves.len())]);
+        }
+        if !opening.board.game_over() && seen.insert(&opening.board) {
+            openings.push(opening);
+        }
+    }
+    openings
+}
+
+/// Scores every opening with a short search, splitting them between the given searchers,
+/// one thread each, and keeps those scored within `window` of even.
+pub fn filter_by_score(
+    mut openings: Vec<Opening>,
+    searches: &mut [Search],
+    limits: Limits,
+    window: i32,
+) -> Vec<Opening> {
+    for_each_split(&mut openings, searches, |search, opening| {
+        opening.score = Some(search.search(&opening.board, limits).score);
+    });
+    openings.retain(|opening| opening.score.is_some_and(|score| score.abs() <= window));
+    openings
+}
+
+/// Writes one EPD line per opening: the four fields of its FEN, then its score as a `ce`
+/// operation and its moves as a `pv` operation where known, each ended by `;`.
+pub fn write_epd(out: &mut impl Write, openings: &[Opening]) -> io::Result<()> {
+    for opening in openings {
+        write!(out, "{}", opening.board.fen_buf())?;
+        if let Some(score) = opening.score {
+            write!(out, " ce {score};")?;
+        }
+        if !opening.moves.is_empty() {
+            let moves: Vec<String> = opening.moves.iter().map(Move::to_string).collect();
+            write!(out, " pv {};", moves.join(" "))?;
+        }
+        writeln!(out)?;
+    }
+    out.flush()
+}
+
+/// Reads one opening position per line from a FEN or EPD file, ignoring blank lines and
+/// `#` comments. EPD operations after the four FEN fields are checked as [`write_epd`]
+/// writes them, and operations other than `ce` and `pv` are skipped.
+pub fn load_openings(path: &Path) -> io::Result<Vec<Board>> {
+    let text = fs::read_to_string(path)?;
+    let mut openings = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (board, _, _) = parse_epd(line)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {e}: {line}", i + 1)))?;
+        openings.push(board);
+    }
+    Ok(openings)
+}
+
+/// The position, `ce` score and `pv` moves of an EPD line.
+fn parse_epd(line: &str) -> Result<(Board, Option<i32>, Vec<Move>), String> {
+    let mut rest = line;
+    for _ in 0..4 {
+        rest = rest.trim_start();
+        rest = &rest[rest.find(char::is_whitespace).unwrap_or(rest.len())..];
+    }
+    let board: Board = line[..line.len() - rest.len()].parse().map_err(|e: FenError| e.to_string())?;
+    let (mut score, mut moves) = (None, Vec::new());
+    let mut operations = rest.split(';');
+    let last = operations.next_back().unwrap_or("");
+    if !last.trim().is_empty() {
+        return Err(format!("operation not ended by ';': {}", last.trim()));
+    }
+    for operation in operations {
+        let mut words = operation.split_whitespace();
+        match words.next() {
+            Some("ce") => {
+                let value = words.next().and_then(|value| value.parse().ok());
+                score = Some(value.filter(|_| words.next().is_none()).ok_or("invalid ce operation")?);
+            }
+            Some("pv") => {
+                moves = words.map(|mv| mv.parse::<Move>().map_err(|e| format!("{e}: {mv}"))).collect::<Result<_, _>>()?;
+            }
+            Some(_) => {}
+            None => return Err("empty operation".into()),
+        }
+    }
+    Ok((board, score, moves))
+}
+
+/// A book move and how often it should be chosen, relative to the other moves of its position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookMove {
+    pub mv: Move,
+    pub weight: u32,
+}
+
+/// Weighted moves for positions, looked up by `Board::key`.
+///
+/// Books are stored as text, one `key move weight` line per move with the key in hex.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Book {
+    entries: HashMap<u64, Vec<BookMove>>,
+}
+
+impl Book {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A book holding every move of every opening, weighted by how many openings play it.
+    pub fn from_openings(openings: &[Opening]) -> Self {
+        let mut book = Self::new();
+        for opening in openings {
+            book.add_line(&opening.root, &opening.moves, 1);
+        }
+        book
+    }
+
+    /// The number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds `weight` to `mv` in the position with `key`.
+    pub fn add(&mut self, key: u64, mv: Move, weight: u32) {
+        let moves = self.entries.entry(key).or_default();
+        match moves.iter_mut().find(|m| m.mv == mv) {
+            Some(m) => m.weight = m.weight.saturating_add(weight),
+            None => moves.push(BookMove { mv, weight }),
+        }
+    }
+
+    /// Adds `weight` to every move of a line played from `root`.
+    pub fn add_line(&mut self, root: &Board, moves: &[Move], weight: u32) {
+        let mut board = *root;
+        for &mv in moves {
+            self.add(board.key(), mv, weight);
+            board.make_move(mv);
+        }
+    }
+
+    /// The moves of the position with `key`, or nothing if it is not in the book.
+    pub fn lookup(&self, key: u64) -> &[BookMove] {
+        self.entries.get(&key).map_or(&[], Vec::as_slice)
+    }
+
+    /// Picks a legal move of `board` at random, in proportion to the weights.
+    pub fn pick(&self, board: &Board, rng: &mut Rng) -> Option<Move> {
+        let moves: Vec<BookMove> =
+            self.lookup(board.key()).iter().copied().filter(|m| m.weight > 0 && board.is_legal(m.mv)).collect();
+        let total: u64 = moves.iter().map(|m| u64::from(m.weight)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut target = rng.next_u64() % total;
+        for m in moves {
+            if target < u64::from(m.weight) {
+                return Some(m.mv);
+            }
+            target -= u64::from(m.weight);
+        }
+        unreachable!("the target is below the total weight")
+    }
+
+    /// Writes the book, sorted by key so that equal books give equal files.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut keys: Vec<u64> = self.entries.keys().copied().collect();
+        keys.sort_unstable();
+        for key in keys {
+            for m in &self.entries[&key] {
+                writeln!(out, "{key:016x} {} {}", m.mv, m.weight)?;
+            }
+        }
+        out.flush()
+    }
+
+    pub fn read(input: impl BufRead) -> io::Result<Self> {
+        let mut book = Self::new();
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {line}", i + 1));
+            let mut fields = line.split_whitespace();
+            let (Some(key), Some(mv), Some(weight), None) = (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid());
+            };
+            let key = u64::from_str_radix(key, 16).map_err(|_| invalid())?;
+            let mv = mv.parse().map_err(|_| invalid())?;
+            let weight = weight.parse().map_err(|_| invalid())?;
+            book.add(key, mv, weight);
+        }
+        Ok(book)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{enumerate, filter_by_score, parse_epd, sample, write_epd, Book};
+    use crate::{
+        dataset::Dedup,
+        rng::Rng,
+        search::{Limits, Search},
+        Board,
+    };
+
+    #[test]
+    fn enumerates_and_samples_distinct_openings() {
+        let root = Board::default();
+        let one = enumerate(&[root], 1, Dedup::Off);
+        let mut moves = 0;
+        root.generate_moves(|_| {
+            moves += 1;
+            false
+        });
+        assert_eq!(one.len(), moves);
+        assert!(enumerate(&[root], 1, Dedup::Symmetric).len() < one.len());
+
+        let two = enumerate(&[root], 2, Dedup::Position);
+        let mut keys: Vec<u64> = two.iter().map(|o| o.board.key()).collect();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), two.len());
+        assert!(two.iter().all(|o| o.moves.len() == 2));
+
+        let sampled = sample(&[root], 4, 50, Dedup::Position, &mut Rng::new(7));
+        assert_eq!(sampled.len(), 50);
+        for opening in &sampled {
+            let mut board = opening.root;
+            opening.moves.iter().for_each(|&mv| board.make_move(mv));
+            assert_eq!(board, opening.board);
+        }
+    }
+
+    #[test]
+    fn filters_by_score_and_writes_books() {
+        let openings = enumerate(&[Board::default()], 2, Dedup::Position);
+        let count = openings.len();
+        let mut searches = [Search::new(1), Search::new(1)];
+        let kept = filter_by_score(openings, &mut searches, Limits::depth(2), 100);
+        assert!(!kept.is_empty() && kept.len() < count);
+        assert!(kept.iter().all(|o| o.score.unwrap().abs() <= 100));
+
+        let mut epd = Vec::new();
+        write_epd(&mut epd, &kept).unwrap();
+        let text = String::from_utf8(epd).unwrap();
+        for (line, opening) in text.lines().zip(&kept) {
+            let moves = format!("{} {}", opening.moves[0], opening.moves[1]);
+            assert_eq!(line, format!("{} ce {}; pv {moves};", opening.board.fen(), opening.score.unwrap()));
+            assert_eq!(parse_epd(line), Ok((opening.board, opening.score, opening.moves.clone())));
+        }
+        let fen = Board::default().fen();
+        assert_eq!(parse_epd(&fen), Ok((Board::default(), None, Vec::new())));
+        assert_eq!(parse_epd(&format!("{fen} bm a2; id \"start\";")), Ok((Board::default(), None, Vec::new())));
+        assert!(parse_epd(&format!("{fen}; ce 3")).is_err());
+        assert!(parse_epd(&format!("{fen} ce 3")).is_err());
+        assert!(parse_epd(&format!("{fen} ce x;")).is_err());
+
+        let book = Book::from_openings(&kept);
+        let root = Board::default();
+        let total: u32 = book.lookup(root.key()).iter().map(|m| m.weight).sum();
+        assert_eq!(total as usize, kept.len());
+        let mv = book.pick(&root, &mut Rng::new(1)).unwrap();
+        assert!(kept.iter().any(|o| o.moves[0] == mv));
+        assert_eq!(book.pick(&kept[0].board, &mut Rng::new(1)), None);
+
+        let mut text = Vec::new();
+        book.write(&mut text).unwrap();
+        assert_eq!(Book::read(text.as_slice()).unwrap(), book);
+    }
+}
@@ -1,40 +1,218 @@
-use crate::Board;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "std")]
+use std::collections::HashSet;
+
+use crate::{
+    mix,
+    walk::{walk, Control, TreeVisitor},
+    Board, Move,
+};
 
 pub fn perft(board: &Board, depth: u8) -> u64 {
-    if depth == 0 {
-        return 1;
+    struct Perft {
+        depth: u8,
+        nodes: u64,
+    }
+
+    impl TreeVisitor for Perft {
+        fn enter(&mut self, board: &Board, _mv: Option<Move>, depth: u8) -> Control {
+            if depth + 1 < self.depth {
+                return Control::Continue;
+            }
+            // count the moves of the last ply rather than making them.
+            board.generate_moves(|_| {
+                self.nodes += 1;
+                false
+            });
+            Control::Prune
+        }
+
+        fn leaf(&mut self, _board: &Board, _mv: Option<Move>, _depth: u8) -> Control {
+            self.nodes += 1;
+            Control::Continue
+        }
+    }
+
+    let mut perft = Perft { depth, nodes: 0 };
+    walk(board, depth, &mut perft);
+    perft.nodes
+}
+
+#[cfg(feature = "alloc")]
+pub fn generate_depth_n_fens(board: Board, fen_receiver: impl FnMut(String), depth: u8) {
+    struct Fens<F>(F);
+
+    impl<F: FnMut(String)> TreeVisitor for Fens<F> {
+        fn leaf(&mut self, board: &Board, _mv: Option<Move>, _depth: u8) -> Control {
+            (self.0)(board.fen());
+            Control::Continue
+        }
+    }
+
+    walk(&board, depth, &mut Fens(fen_receiver));
+}
+
+/// Which positions count as the same when looking for distinct positions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionKey {
+    /// Pieces, walls, side to move and both move counters.
+    Full,
+    /// Pieces, walls and side to move, ignoring the move counters.
+    Position,
+    /// Pieces, walls and side to move up to the symmetries of the board.
+    Symmetric,
+}
+
+impl PositionKey {
+    pub fn key(self, board: &Board) -> u64 {
+        match self {
+            PositionKey::Full => board.key() ^ mix(u64::from(board.ply) << 8 | u64::from(board.halfmove)),
+            PositionKey::Position => board.key(),
+            PositionKey::Symmetric => board.canonical_key(),
+        }
+    }
+}
+
+/// The distinct positions reachable from `board` in exactly `depth` moves, in the order
+/// they are first reached.
+///
+/// Positions are expanded one ply at a time, keeping only the first position with each key,
+/// so transpositions are only expanded once.
+#[cfg(feature = "std")]
+pub fn unique_at_depth(board: &Board, depth: u8, key: PositionKey) -> Vec<Board> {
+    let mut layer = vec![*board];
+    for _ in 0..depth {
+        layer = next_layer(&layer, key);
+    }
+    layer
+}
+
+/// The distinct positions reachable from `board` in at most `depth` moves, `board` itself
+/// included, in the order they are first reached.
+#[cfg(feature = "std")]
+pub fn unique_within_depth(board: &Board, depth: u8, key: PositionKey) -> Vec<Board> {
+    let mut seen = HashSet::new();
+    let mut positions = Vec::new();
+    let mut layer = vec![*board];
+    for ply in 0..=depth {
+        positions.extend(layer.iter().filter(|board| seen.insert(key.key(board))));
+        if ply < depth {
+            layer = next_layer(&layer, key);
+        }
     }
+    positions
+}
 
-    if depth == 1 {
-        let mut count = 0;
-        board.generate_moves(|_| {
-            count += 1;
-            false
+/// The number of positions [`unique_at_depth`] returns, without keeping the last layer: only
+/// its keys are stored.
+#[cfg(feature = "std")]
+pub fn count_unique_at_depth(board: &Board, depth: u8, key: PositionKey) -> u64 {
+    let Some(last) = depth.checked_sub(1) else {
+        return 1;
+    };
+    let mut keys = HashSet::new();
+    for board in unique_at_depth(board, last, key) {
+        for_each_child(&board, |child| {
+            keys.insert(key.key(child));
         });
-        return count;
     }
+    keys.len() as u64
+}
 
-    let mut count = 0;
+/// The number of positions [`unique_within_depth`] returns, keeping only their keys and the
+/// boards of one layer at a time.
+#[cfg(feature = "std")]
+pub fn count_unique_within_depth(board: &Board, depth: u8, key: PositionKey) -> u64 {
+    let mut seen = HashSet::from([key.key(board)]);
+    let mut layer = vec![*board];
+    for ply in 1..=depth {
+        if ply < depth {
+            layer = next_layer(&layer, key);
+            seen.extend(layer.iter().map(|board| key.key(board)));
+        } else {
+            for board in &layer {
+                for_each_child(board, |child| {
+                    seen.insert(key.key(child));
+                });
+            }
+        }
+    }
+    seen.len() as u64
+}
+
+#[cfg(feature = "std")]
+fn for_each_child(board: &Board, mut f: impl FnMut(&Board)) {
     board.generate_moves(|mv| {
-        let mut board = *board;
-        board.make_move(mv);
-        count += perft(&board, depth - 1);
+        let mut child = *board;
+        child.make_move(mv);
+        f(&child);
         false
     });
+}
 
-    count
+/// The distinct positions one move after those of `layer`.
+#[cfg(feature = "std")]
+fn next_layer(layer: &[Board], key: PositionKey) -> Vec<Board> {
+    let mut seen = HashSet::new();
+    let mut next = Vec::new();
+    for board in layer {
+        for_each_child(board, |child| {
+            if seen.insert(key.key(child)) {
+                next.push(*child);
+            }
+        });
+    }
+    next
 }
 
-pub fn generate_depth_n_fens(board: Board, mut fen_receiver: impl FnMut(String) + Copy, depth: u8) {
-    if depth == 0 {
-        fen_receiver(board.fen());
-        return;
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{
+        count_unique_at_depth, count_unique_within_depth, generate_depth_n_fens, perft, unique_at_depth,
+        unique_within_depth, PositionKey,
+    };
+    use crate::Board;
+
+    #[test]
+    fn unique_positions_match_distinct_fens() {
+        let board = Board::default();
+        let mut fens = Vec::new();
+        generate_depth_n_fens(board, |fen| fens.push(fen), 3);
+        assert_eq!(fens.len() as u64, perft(&board, 3));
+
+        let full: HashSet<&str> = fens.iter().map(String::as_str).collect();
+        let position: HashSet<String> =
+            fens.iter().map(|fen| fen.split(' ').take(2).collect::<Vec<_>>().join(" ")).collect();
+        let symmetric: HashSet<u64> = fens.iter().map(|fen| fen.parse::<Board>().unwrap().canonical_key()).collect();
+        assert!(full.len() < fens.len() && position.len() < full.len() && symmetric.len() < position.len());
+
+        assert_eq!(unique_at_depth(&board, 3, PositionKey::Full).len(), full.len());
+        assert_eq!(unique_at_depth(&board, 3, PositionKey::Position).len(), position.len());
+        assert_eq!(unique_at_depth(&board, 3, PositionKey::Symmetric).len(), symmetric.len());
+
+        for key in [PositionKey::Full, PositionKey::Position, PositionKey::Symmetric] {
+            for depth in 0..=3 {
+                assert_eq!(count_unique_at_depth(&board, depth, key), unique_at_depth(&board, depth, key).len() as u64);
+                let within = unique_within_depth(&board, depth, key).len() as u64;
+                assert_eq!(count_unique_within_depth(&board, depth, key), within);
+            }
+        }
     }
 
-    board.generate_moves(|mv| {
-        let mut board = board;
-        board.make_move(mv);
-        generate_depth_n_fens(board, fen_receiver, depth - 1);
-        false
-    });
-}
\ No newline at end of file
+    #[test]
+    fn unique_positions_within_depth() {
+        let board = Board::default();
+        let exact: usize = (0..=3).map(|depth| unique_at_depth(&board, depth, PositionKey::Full).len()).sum();
+        // with the move counters, a position at one depth can never be reached at another.
+        assert_eq!(unique_within_depth(&board, 3, PositionKey::Full).len(), exact);
+
+        let within = unique_within_depth(&board, 3, PositionKey::Position);
+        assert_eq!(within[0], board);
+        assert!(within.len() < exact);
+        let keys: HashSet<u64> = within.iter().map(Board::key).collect();
+        assert_eq!(keys.len(), within.len());
+    }
+}
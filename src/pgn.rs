@@ -0,0 +1,701 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, BufRead},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    tree::{GameTree, NodeId},
+    uai::Score,
+    Board, FenError, Move, Player,
+};
+
+/// The tags of the seven tag roster, which every game has, first and in this order.
+const ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Tags that are written from the game itself rather than kept as tags.
+const DERIVED: [&str; 3] = ["Result", "SetUp", "FEN"];
+
+const MAX_LINE: usize = 80;
+
+#[derive(Debug)]
+pub enum PgnError {
+    Io(io::Error),
+    InvalidTag(String),
+    InvalidFen(FenError),
+    /// A movetext token that is not a move, with the ply it was found at.
+    InvalidMove { ply: usize, text: String },
+    IllegalMove { ply: usize, mv: Move },
+    UnterminatedComment,
+    UnterminatedVariation,
+    UnexpectedToken(String),
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::Io(e) => write!(f, "{e}"),
+            PgnError::InvalidTag(tag) => write!(f, "invalid tag: {tag}"),
+            PgnError::InvalidFen(e) => write!(f, "invalid FEN tag: {e}"),
+            PgnError::InvalidMove { ply, text } => write!(f, "invalid move at ply {ply}: {text}"),
+            PgnError::IllegalMove { ply, mv } => write!(f, "illegal move at ply {ply}: {mv}"),
+            PgnError::UnterminatedComment => write!(f, "unterminated comment"),
+            PgnError::UnterminatedVariation => write!(f, "unterminated variation"),
+            PgnError::UnexpectedToken(token) => write!(f, "unexpected token: {token}"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PgnError::Io(e) => Some(e),
+            PgnError::InvalidFen(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PgnError {
+    fn from(e: io::Error) -> Self {
+        PgnError::Io(e)
+    }
+}
+
+/// An engine's evaluation of a move, written at the start of its comment as `+0.35/7`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Eval {
+    pub score: Score,
+    pub depth: Option<u32>,
+}
+
+impl Display for Eval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.depth {
+            Some(depth) => write!(f, "{}/{depth}", self.score),
+            None => write!(f, "{}", self.score),
+        }
+    }
+}
+
+impl FromStr for Eval {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (score, depth) = match s.split_once('/') {
+            Some((score, depth)) => (score, Some(depth.parse().map_err(|_| "invalid depth")?)),
+            None => (s, None),
+        };
+        Ok(Self { score: score.parse()?, depth })
+    }
+}
+
+/// A game record: its tags, its moves with any variations, and its result.
+///
+/// The `Result`, `SetUp` and `FEN` tags are not kept with the other tags, but written from
+/// `result` and the start position of `tree`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Game {
+    tags: Vec<(String, String)>,
+    /// The moves. A comment before the first move is kept at the root.
+    pub tree: GameTree,
+    /// The winner, `Some(None)` for a draw, or `None` if the result is unknown.
+    pub result: Option<Option<Player>>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new(Board::default())
+    }
+}
+
+impl Game {
+    pub fn new(start: Board) -> Self {
+        Self { tags: Vec::new(), tree: GameTree::new(start), result: None }
+    }
+
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// Sets a tag, replacing any earlier value. Derived tags such as `Result` are ignored.
+    pub fn set_tag(&mut self, name: &str, value: impl Into<String>) {
+        if DERIVED.contains(&name) {
+            return;
+        }
+        let value = value.into();
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value,
+            None => self.tags.push((name.to_owned(), value)),
+        }
+    }
+
+    /// The moves of the main line.
+    pub fn moves(&self) -> Vec<Move> {
+        self.tree.moves_to(self.tree.mainline_end(self.tree.root()))
+    }
+
+    /// The position at the end of the main line.
+    pub fn board(&self) -> Board {
+        self.tree.board(self.tree.mainline_end(self.tree.root()))
+    }
+
+    /// Plays `mv` at the end of the main line, if it is legal there.
+    pub fn push(&mut self, mv: Move) -> Result<NodeId, PgnError> {
+        let end = self.tree.mainline_end(self.tree.root());
+        self.tree.add_move(end, mv).ok_or_else(|| PgnError::IllegalMove { ply: self.tree.moves_to(end).len() + 1, mv })
+    }
+
+    fn result_str(&self) -> &'static str {
+        match self.result {
+            Some(Some(Player::White)) => "1-0",
+            Some(Some(Player::Black)) => "0-1",
+            Some(None) => "1/2-1/2",
+            None => "*",
+        }
+    }
+}
+
+/// Today's date in PGN form, such as `2024.03.09`.
+pub fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() / 86_400) as i64;
+    // Howard Hinnant's days-to-civil conversion.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}.{month:02}.{day:02}")
+}
+
+fn write_tag(f: &mut Formatter<'_>, name: &str, value: &str) -> fmt::Result {
+    writeln!(f, "[{name} \"{}\"]", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Formats a comment, which cannot contain a closing brace.
+fn comment_token(eval: Option<Eval>, comment: Option<&str>) -> Option<String> {
+    let text = match (eval, comment) {
+        (None, None) => return None,
+        (Some(eval), None) => eval.to_string(),
+        (None, Some(comment)) => comment.to_owned(),
+        (Some(eval), Some(comment)) => format!("{eval} {comment}"),
+    };
+    Some(format!("{{{}}}", text.replace('}', ")")))
+}
+
+/// Appends the tokens of a move at `id`, played from `board`, which it updates. The move
+/// number is written for black moves too if `number` is set. Returns whether the move has a
+/// comment.
+fn push_move(tree: &GameTree, id: NodeId, board: &mut Board, number: bool, tokens: &mut Vec<String>) -> bool {
+    let node = tree.node(id);
+    let mv = node.mv.expect("only the root has no move");
+    match board.turn() {
+        Player::White => tokens.push(format!("{}.", board.fullmove())),
+        Player::Black if number => tokens.push(format!("{}...", board.fullmove())),
+        Player::Black => {}
+    }
+    tokens.push(mv.to_string());
+    tokens.extend(node.nags.iter().map(|nag| format!("${nag}")));
+    let comment = comment_token(node.eval, node.comment.as_deref());
+    let commented = comment.is_some();
+    tokens.extend(comment);
+    board.make_move(mv);
+    commented
+}
+
+/// Appends the movetext of everything after `id`, whose position is `board`: its main line,
+/// with the other continuations of each move as variations in parentheses.
+fn push_movetext(tree: &GameTree, mut id: NodeId, mut board: Board, mut number: bool, tokens: &mut Vec<String>) {
+    while let Some((&main, variations)) = tree.node(id).children().split_first() {
+        let before = board;
+        let commented = push_move(tree, main, &mut board, number, tokens);
+        for &variation in variations {
+            let mut line = Vec::new();
+            let mut board = before;
+            let commented = push_move(tree, variation, &mut board, true, &mut line);
+            push_movetext(tree, variation, board, commented, &mut line);
+            line[0].insert(0, '(');
+            line.last_mut().unwrap().push(')');
+            tokens.extend(line);
+        }
+        number = commented || !variations.is_empty();
+        id = main;
+    }
+}
+
+/// Writes tokens separated by spaces, wrapping lines before they grow too long.
+fn write_wrapped(f: &mut Formatter<'_>, tokens: &[String]) -> fmt::Result {
+    let mut len = 0;
+    for token in tokens {
+        if len > 0 && len + 1 + token.len() > MAX_LINE {
+            writeln!(f)?;
+            len = 0;
+        }
+        if len > 0 {
+            write!(f, " ")?;
+            len += 1;
+        }
+        write!(f, "{token}")?;
+        len += token.len();
+    }
+    writeln!(f)
+}
+
+impl Display for Game {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for name in ROSTER {
+            let value = match name {
+                "Result" => self.result_str(),
+                "Date" => self.tag(name).unwrap_or("????.??.??"),
+                _ => self.tag(name).unwrap_or("?"),
+            };
+            write_tag(f, name, value)?;
+        }
+        if *self.tree.start() != Board::default() {
+            write_tag(f, "SetUp", "1")?;
+            write_tag(f, "FEN", &self.tree.start().fen_buf())?;
+        }
+        for (name, value) in &self.tags {
+            if !ROSTER.contains(&name.as_str()) && !DERIVED.contains(&name.as_str()) {
+                write_tag(f, name, value)?;
+            }
+        }
+        writeln!(f)?;
+
+        let mut tokens = Vec::new();
+        let root = self.tree.node(self.tree.root());
+        tokens.extend(comment_token(root.eval, root.comment.as_deref()));
+        push_movetext(&self.tree, self.tree.root(), *self.tree.start(), true, &mut tokens);
+        tokens.push(self.result_str().to_owned());
+        write_wrapped(f, &tokens)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Token<'a> {
+    Tag(String, String),
+    Comment(&'a str),
+    Nag(u8),
+    OpenVariation,
+    CloseVariation,
+    Result(Option<Option<Player>>),
+    /// A move, possibly with a move number in front or annotation symbols after.
+    Word(&'a str),
+}
+
+/// Splits PGN text into tokens.
+pub(crate) fn tokenize(text: &str) -> Result<Vec<Token<'_>>, PgnError> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start();
+        let Some(c) = rest.chars().next() else { break };
+        match c {
+            '[' => {
+                let end = tag_end(rest).ok_or_else(|| PgnError::InvalidTag(rest.lines().next().unwrap_or("").into()))?;
+                tokens.push(parse_tag(&rest[..=end])?);
+                rest = &rest[end + 1..];
+            }
+            '{' => {
+                let end = rest.find('}').ok_or(PgnError::UnterminatedComment)?;
+                tokens.push(Token::Comment(rest[1..end].trim()));
+                rest = &rest[end + 1..];
+            }
+            ';' => {
+                let end = rest.find('\n').unwrap_or(rest.len());
+                tokens.push(Token::Comment(rest[1..end].trim()));
+                rest = &rest[end..];
+            }
+            '%' if text.len() == rest.len() || text.as_bytes()[text.len() - rest.len() - 1] == b'\n' => {
+                // escaped lines, for the use of other programs.
+                rest = &rest[rest.find('\n').unwrap_or(rest.len())..];
+            }
+            '(' | ')' => {
+                tokens.push(if c == '(' { Token::OpenVariation } else { Token::CloseVariation });
+                rest = &rest[1..];
+            }
+            _ => {
+                let end = rest.find(|c: char| c.is_whitespace() || "[]{}();".contains(c)).unwrap_or(rest.len());
+                let word = &rest[..end];
+                rest = &rest[end..];
+                tokens.push(match word {
+                    "1-0" => Token::Result(Some(Some(Player::White))),
+                    "0-1" => Token::Result(Some(Some(Player::Black))),
+                    "1/2-1/2" => Token::Result(Some(None)),
+                    "*" => Token::Result(None),
+                    _ => match word.strip_prefix('$') {
+                        Some(nag) => Token::Nag(nag.parse().map_err(|_| PgnError::UnexpectedToken(word.into()))?),
+                        None => Token::Word(word),
+                    },
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// The index of the `]` closing the tag at the start of `text`, skipping any inside its
+/// quoted value.
+fn tag_end(text: &str) -> Option<usize> {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ']' if !quoted => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_tag(text: &str) -> Result<Token<'static>, PgnError> {
+    let invalid = || PgnError::InvalidTag(text.to_owned());
+    let inner = text[1..text.len() - 1].trim();
+    let (name, value) = inner.split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let value = value.trim().strip_prefix('"').and_then(|v| v.strip_suffix('"')).ok_or_else(invalid)?;
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        unescaped.push(if c == '\\' { chars.next().unwrap_or('\\') } else { c });
+    }
+    Ok(Token::Tag(name.to_owned(), unescaped))
+}
+
+/// Reads the move out of a movetext word, dropping any move number in front of it and
+/// annotation symbols after it. Returns `None` for a bare move number.
+pub(crate) fn parse_word(word: &str, ply: usize) -> Result<Option<(Move, &str)>, PgnError> {
+    let digits = word.len() - word.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let word = if digits > 0 && word[digits..].starts_with('.') {
+        word[digits..].trim_start_matches('.')
+    } else {
+        word
+    };
+    if word.is_empty() {
+        return Ok(None);
+    }
+    let annotation_at = word.find(['!', '?', '+', '#']).unwrap_or(word.len());
+    let (mv, annotation) = word.split_at(annotation_at);
+    let mv = mv.parse().map_err(|_| PgnError::InvalidMove { ply, text: word.to_owned() })?;
+    Ok(Some((mv, annotation)))
+}
+
+/// Splits a comment into the evaluation at its start, if it has one, and the rest.
+pub(crate) fn split_eval(comment: &str) -> (Option<Eval>, Option<String>) {
+    let (first, rest) = comment.split_once(char::is_whitespace).unwrap_or((comment, ""));
+    let (eval, text) = match first.parse::<Eval>() {
+        Ok(eval) => (Some(eval), rest.trim()),
+        Err(_) => (None, comment),
+    };
+    (eval, (!text.is_empty()).then(|| text.to_owned()))
+}
+
+fn append_comment(comment: &mut Option<String>, text: String) {
+    match comment {
+        Some(existing) => {
+            existing.push(' ');
+            existing.push_str(&text);
+        }
+        None => *comment = Some(text),
+    }
+}
+
+/// The glyph of a move annotation such as `!?`.
+fn annotation_nag(annotation: &str) -> Option<u8> {
+    match annotation {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+/// Parses a single game, with its variations.
+impl FromStr for Game {
+    type Err = PgnError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut game = Game::default();
+        let mut tag_result = None;
+        let mut result = None;
+        let mut in_movetext = false;
+        let mut node = game.tree.root();
+        // the nodes to go back to when the open variations are closed.
+        let mut variations = Vec::new();
+        // a comment at the start of a variation, which belongs to its first move.
+        let mut starting_comment: Option<Option<String>> = None;
+        for token in tokens {
+            if !matches!(token, Token::Tag(..)) {
+                in_movetext = true;
+            }
+            match token {
+                Token::Tag(..) if in_movetext => {
+                    return Err(PgnError::UnexpectedToken("tag after movetext".into()));
+                }
+                Token::Tag(name, value) => match name.as_str() {
+                    "FEN" => {
+                        game.tree = GameTree::new(value.parse().map_err(PgnError::InvalidFen)?);
+                        node = game.tree.root();
+                    }
+                    "Result" => tag_result = Some(value),
+                    "SetUp" => {}
+                    _ => game.set_tag(&name, value),
+                },
+                Token::Comment(text) => match &mut starting_comment {
+                    Some(comment) => append_comment(comment, text.to_owned()),
+                    None => {
+                        let (eval, text) = split_eval(text);
+                        let node = game.tree.node_mut(node);
+                        node.eval = node.eval.or(eval);
+                        if let Some(text) = text {
+                            append_comment(&mut node.comment, text);
+                        }
+                    }
+                },
+                Token::Nag(nag) => {
+                    if node != game.tree.root() {
+                        game.tree.node_mut(node).nags.push(nag);
+                    }
+                }
+                Token::OpenVariation => {
+                    let parent = game.tree.node(node).parent().ok_or(PgnError::UnexpectedToken("(".into()))?;
+                    variations.push(node);
+                    node = parent;
+                    starting_comment = Some(None);
+                }
+                Token::CloseVariation => {
+                    node = variations.pop().ok_or(PgnError::UnexpectedToken(")".into()))?;
+                    starting_comment = None;
+                }
+                Token::Result(r) => {
+                    result = Some(r);
+                    break;
+                }
+                Token::Word(word) => {
+                    let ply = game.tree.moves_to(node).len() + 1;
+                    let Some((mv, annotation)) = parse_word(word, ply)? else { continue };
+                    node = game.tree.add_move(node, mv).ok_or(PgnError::IllegalMove { ply, mv })?;
+                    let node = game.tree.node_mut(node);
+                    if let Some(nag) = annotation_nag(annotation) {
+                        node.nags.push(nag);
+                    }
+                    if let Some(Some(comment)) = starting_comment.take() {
+                        append_comment(&mut node.comment, comment);
+                    }
+                }
+            }
+        }
+        if !variations.is_empty() {
+            return Err(PgnError::UnterminatedVariation);
+        }
+
+        game.result = match result {
+            Some(result) => result,
+            None => match tag_result.as_deref() {
+                Some("1-0") => Some(Some(Player::White)),
+                Some("0-1") => Some(Some(Player::Black)),
+                Some("1/2-1/2") => Some(None),
+                _ => None,
+            },
+        };
+        Ok(game)
+    }
+}
+
+/// Reads the games of a PGN file one at a time.
+pub struct PgnReader<R> {
+    inner: R,
+    /// The first line of the next game, if it has been read already.
+    pending: Option<String>,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, pending: None }
+    }
+
+    /// Reads the text of the next game. A game ends where a tag line follows its movetext.
+    pub(crate) fn read_game_text(&mut self) -> io::Result<Option<String>> {
+        let mut text = self.pending.take().unwrap_or_default();
+        let mut in_movetext = false;
+        let mut in_comment = false;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.inner.read_line(&mut line)? == 0 {
+                break;
+            }
+            let trimmed = line.trim_start();
+            if !in_comment && trimmed.starts_with('[') {
+                if in_movetext {
+                    self.pending = Some(line.clone());
+                    break;
+                }
+                // braces in tag values do not open comments.
+                text.push_str(&line);
+                continue;
+            }
+            if !trimmed.is_empty() {
+                in_movetext = true;
+            }
+            for c in line.chars() {
+                match c {
+                    '{' if !in_comment => in_comment = true,
+                    '}' if in_comment => in_comment = false,
+                    ';' if !in_comment => break,
+                    _ => {}
+                }
+            }
+            text.push_str(&line);
+        }
+        Ok((!text.trim().is_empty()).then_some(text))
+    }
+
+    pub fn read_game(&mut self) -> Result<Option<Game>, PgnError> {
+        match self.read_game_text()? {
+            Some(text) => text.parse().map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<Game, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_game().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Eval, Game, PgnError, PgnReader};
+    use crate::{uai::Score, Board, Player};
+
+    const SAMPLE: &str = r#"[Event "Test \"match\""]
+[Site "?"]
+[Date "2024.01.02"]
+[Round "1"]
+[White "alpha"]
+[Black "beta"]
+[Result "0-1"]
+[TimeControl "10+0.1"]
+
+{opening from the book} 1. f1 {+1.00/3} b1 {-1.00/2 a quiet move} 2. g2 a2 $1
+3. a6! (3. b6 {or} 3... f6 (3... b2 4. c7) (3... c1) 4. c7) 3... g6 {-0.50} 4. b7 f7
+{White makes an illegal move: a1} 0-1
+"#;
+
+    #[test]
+    fn parses_tags_moves_and_comments() {
+        let game: Game = SAMPLE.parse().unwrap();
+        assert_eq!(game.tag("Event"), Some("Test \"match\""));
+        assert_eq!(game.tag("TimeControl"), Some("10+0.1"));
+        assert_eq!(game.result, Some(Some(Player::Black)));
+
+        let tree = &game.tree;
+        let main: Vec<_> = tree.mainline(tree.root()).map(|id| tree.node(id)).collect();
+        assert_eq!(tree.node(tree.root()).comment.as_deref(), Some("opening from the book"));
+        assert_eq!(main.len(), 8);
+        assert_eq!(main[0].eval, Some(Eval { score: Score::Cp(100), depth: Some(3) }));
+        assert_eq!(main[1].comment.as_deref(), Some("a quiet move"));
+        assert_eq!((main[3].nags.as_slice(), main[4].nags.as_slice()), ([1].as_slice(), [1].as_slice()));
+        assert_eq!(main[5].eval, Some(Eval { score: Score::Cp(-50), depth: None }));
+        assert_eq!(main[7].comment.as_deref(), Some("White makes an illegal move: a1"));
+        assert_eq!(game.moves().len(), 8);
+    }
+
+    #[test]
+    fn writes_and_reads_variations() {
+        let game: Game = SAMPLE.parse().unwrap();
+        let tree = &game.tree;
+        let a6 = tree.mainline(tree.root()).nth(4).unwrap();
+        let b6 = tree.node(tree.node(a6).parent().unwrap()).children()[1];
+        assert_eq!(tree.node(b6).comment.as_deref(), Some("or"));
+        let f6 = tree.node(b6).children();
+        assert_eq!(f6.len(), 3, "f6, b2 and c1 answer b6");
+        assert_eq!(tree.moves_to(tree.mainline_end(f6[1])).len(), 7);
+
+        let text = game.to_string();
+        assert!(text.contains("3. a6 $1 (3. b6 {or} 3... f6 (3... b2 4. c7) (3... c1) 4. c7) 3... g6"), "{text}");
+        let reparsed: Game = text.parse().unwrap();
+        assert_eq!(reparsed, game);
+    }
+
+    #[test]
+    fn writes_and_reads_games_from_positions() {
+        let start: Board = "x5o/7/2-1-2/7/2-1-2/7/o5x o 3 12".parse().unwrap();
+        let mut game = Game::new(start);
+        game.set_tag("White", "one");
+        game.set_tag("Result", "1-0");
+        let mut moves = Vec::new();
+        start.generate_moves(|mv| {
+            moves.push(mv);
+            true
+        });
+        let first = game.push(moves[0]).unwrap();
+        game.tree.node_mut(first).comment = Some("{braces}".into());
+        game.result = Some(None);
+
+        let text = game.to_string();
+        assert!(text.contains("[FEN \"x5o/7/2-1-2/7/2-1-2/7/o5x o 3 12\"]"));
+        assert!(text.contains(&format!("12... {} {{{{braces)}}", moves[0])));
+        assert!(text.contains("[Result \"1/2-1/2\"]"));
+        assert!(text.trim_end().ends_with("1/2-1/2"));
+
+        // a second game in the same file, which must be split off from the first.
+        let file = format!("{text}\n{SAMPLE}");
+        let games: Vec<Game> = PgnReader::new(file.as_bytes()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(*games[0].tree.start(), start);
+        assert_eq!(games[0].tree.node(first).comment.as_deref(), Some("{braces)"));
+        assert_eq!(games[1].tag("Black"), Some("beta"));
+    }
+
+    #[test]
+    fn tag_values_roundtrip() {
+        let mut game = Game::default();
+        let value = r#"engine [v2] "quoted" \ back]slash"#;
+        game.set_tag("White", value);
+        game.set_tag("Event", "]");
+        game.set_tag("Annotator", "{x");
+        let reparsed: Game = game.to_string().parse().unwrap();
+        assert_eq!(reparsed.tag("White"), Some(value));
+        assert_eq!(reparsed.tag("Event"), Some("]"));
+        assert_eq!(reparsed.tag("Annotator"), Some("{x"));
+
+        // a brace in a tag must not keep the reader from splitting off the next game.
+        let file = format!("{game}\n{SAMPLE}");
+        let games: Vec<Game> = PgnReader::new(file.as_bytes()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[1].tag("Black"), Some("beta"));
+        assert!(matches!("[Event \"open] *".parse::<Game>(), Err(PgnError::InvalidTag(_))));
+    }
+
+    #[test]
+    fn rejects_illegal_moves() {
+        match "1. f1 b1 2. a1 *".parse::<Game>() {
+            Err(PgnError::IllegalMove { ply: 3, mv }) => assert_eq!(mv.to_string(), "a1"),
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(matches!("1. f1 (1. b7 a1) *".parse::<Game>(), Err(PgnError::IllegalMove { ply: 2, .. })));
+        assert!(matches!("1. f1 zz *".parse::<Game>(), Err(PgnError::InvalidMove { ply: 2, .. })));
+        assert!(matches!("1. f1 {unterminated".parse::<Game>(), Err(PgnError::UnterminatedComment)));
+        assert!(matches!("1. f1 (1. b7".parse::<Game>(), Err(PgnError::UnterminatedVariation)));
+    }
+}
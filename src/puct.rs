@@ -0,0 +1,385 @@
+use std::time::Instant;
+
+use crate::{
+    mcts::{reroot, ArenaNode, Limits, NO_NODE},
+    rng::Rng,
+    Board, Move,
+};
+
+/// The size of the `Move::index` space, and so the length of policy vectors.
+pub const POLICY_LEN: usize = 7 * 7 * 7 * 7;
+
+/// How unvisited children are valued during selection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fpu {
+    /// Unvisited children take this fixed value, in `[-1, 1]`.
+    Absolute(f32),
+    /// Unvisited children take the parent's value minus this reduction.
+    Reduction(f32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Params {
+    /// The exploration constant multiplying the prior term.
+    pub cpuct: f32,
+    pub fpu: Fpu,
+    /// The concentration of the Dirichlet noise mixed into the root priors.
+    pub dirichlet_alpha: f32,
+    /// The weight of the Dirichlet noise at the root. Zero disables noise.
+    pub dirichlet_epsilon: f32,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self { cpuct: 1.5, fpu: Fpu::Reduction(0.25), dirichlet_alpha: 0.3, dirichlet_epsilon: 0.0 }
+    }
+}
+
+/// A policy/value function stand-in: uniform priors over all moves and a neutral value.
+pub fn uniform_policy(_: &Board) -> (Vec<f32>, f32) {
+    (vec![1.0; POLICY_LEN], 0.0)
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    mv: Move,
+    prior: f32,
+    first_child: u32,
+    child_count: u32,
+    visits: u32,
+    /// Sum of values in `[-1, 1]` from the perspective of the player who played `mv`.
+    value_sum: f64,
+}
+
+impl Node {
+    const fn new(mv: Move, prior: f32) -> Self {
+        Self { mv, prior, first_child: NO_NODE, child_count: 0, visits: 0, value_sum: 0.0 }
+    }
+
+    fn q(&self) -> f32 {
+        (self.value_sum / f64::from(self.visits)) as f32
+    }
+}
+
+impl ArenaNode for Node {
+    fn first_child(&self) -> u32 {
+        self.first_child
+    }
+
+    fn set_first_child(&mut self, first_child: u32) {
+        self.first_child = first_child;
+    }
+
+    fn child_count(&self) -> u32 {
+        self.child_count
+    }
+}
+
+/// An AlphaZero-style PUCT search, guided by a user-supplied policy/value function.
+///
+/// The evaluator maps a position to a prior over the `Move::index` space (any non-negative
+/// weights, renormalised over the legal moves) and a value in `[-1, 1]` for the side to move.
+pub struct Puct<E> {
+    board: Board,
+    nodes: Vec<Node>,
+    params: Params,
+    evaluator: E,
+    rng: Rng,
+    root_noised: bool,
+}
+
+impl<E: FnMut(&Board) -> (Vec<f32>, f32)> Puct<E> {
+    pub fn new(board: Board, params: Params, evaluator: E, rng: Rng) -> Self {
+        Self { board, nodes: vec![Node::new(Move::Pass, 1.0)], params, evaluator, rng, root_noised: false }
+    }
+
+    /// The position at the root of the tree.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The number of nodes currently in the tree.
+    pub fn tree_size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The number of visits the root has received.
+    pub fn root_visits(&self) -> u32 {
+        self.nodes[0].visits
+    }
+
+    /// Runs the search until one of `limits` is hit, returning the most visited move,
+    /// or `None` if the root position is terminal.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limits` sets no limit.
+    pub fn search(&mut self, limits: Limits) -> Option<Move> {
+        assert!(!limits.is_unbounded(), "PUCT needs at least one limit");
+        if self.board.game_over() {
+            return None;
+        }
+
+        // a root kept by `advance` already has children and gets its noise before any of them
+        // is selected, and a new root as soon as its first expansion gives it some.
+        let noise = self.params.dirichlet_epsilon > 0.0;
+        if noise && !self.root_noised {
+            self.add_root_noise();
+        }
+        let start = Instant::now();
+        let mut path = Vec::new();
+        loop {
+            if limits.visits.is_some_and(|v| u64::from(self.root_visits()) >= v)
+                || limits.nodes.is_some_and(|n| self.tree_size() >= n)
+                || limits.time.is_some_and(|t| start.elapsed() >= t)
+            {
+                break;
+            }
+            self.iterate(&mut path);
+            if noise && !self.root_noised {
+                self.add_root_noise();
+            }
+        }
+
+        self.best_move()
+    }
+
+    fn iterate(&mut self, path: &mut Vec<usize>) {
+        path.clear();
+        path.push(0);
+        let mut board = self.board;
+        let mut node = 0;
+
+        while self.nodes[node].child_count > 0 {
+            node = self.select_child(node);
+            path.push(node);
+            board.make_move(self.nodes[node].mv);
+        }
+
+        // value from the perspective of the side to move at the leaf.
+        let mut value = match board.outcome() {
+            Some(winner) => match winner {
+                None => 0.0,
+                Some(p) if p == board.turn() => 1.0,
+                Some(_) => -1.0,
+            },
+            None => self.expand(node, &board),
+        };
+
+        for &idx in path.iter().rev() {
+            // each node's value is stored from the perspective of the player who moved into it.
+            value = -value;
+            let node = &mut self.nodes[idx];
+            node.visits += 1;
+            node.value_sum += f64::from(value);
+        }
+    }
+
+    fn select_child(&self, parent: usize) -> usize {
+        let parent_node = &self.nodes[parent];
+        let sqrt_n = (parent_node.visits as f32).sqrt();
+        let fpu = match self.params.fpu {
+            Fpu::Absolute(v) => v,
+            Fpu::Reduction(r) => {
+                // the parent's value is stored for the other player, so flip it.
+                let parent_q = if parent_node.visits > 0 { -parent_node.q() } else { 0.0 };
+                parent_q - r
+            }
+        };
+        let mut best = parent_node.first_child as usize;
+        let mut best_score = f32::NEG_INFINITY;
+        for idx in parent_node.children() {
+            let child = &self.nodes[idx];
+            let q = if child.visits == 0 { fpu } else { child.q() };
+            let u = self.params.cpuct * child.prior * sqrt_n / (1.0 + child.visits as f32);
+            if q + u > best_score {
+                best_score = q + u;
+                best = idx;
+            }
+        }
+        best
+    }
+
+    fn expand(&mut self, node: usize, board: &Board) -> f32 {
+        let (policy, value) = (self.evaluator)(board);
+        debug_assert_eq!(policy.len(), POLICY_LEN);
+        let first_child = self.nodes.len() as u32;
+        let nodes = &mut self.nodes;
+        board.generate_moves(|mv| {
+            nodes.push(Node::new(mv, policy[mv.index()].max(0.0)));
+            false
+        });
+        let children = first_child as usize..self.nodes.len();
+        let total: f32 = self.nodes[children.clone()].iter().map(|n| n.prior).sum();
+        let count = children.len() as f32;
+        for child in &mut self.nodes[children.clone()] {
+            child.prior = if total > 0.0 { child.prior / total } else { 1.0 / count };
+        }
+        let node = &mut self.nodes[node];
+        node.first_child = first_child;
+        node.child_count = children.len() as u32;
+        value
+    }
+
+    fn add_root_noise(&mut self) {
+        let children = self.nodes[0].children();
+        if children.is_empty() {
+            return;
+        }
+        let noise = self.rng.dirichlet(f64::from(self.params.dirichlet_alpha), children.len());
+        let eps = self.params.dirichlet_epsilon;
+        for (child, n) in self.nodes[children].iter_mut().zip(noise) {
+            child.prior = (1.0 - eps) * child.prior + eps * n as f32;
+        }
+        self.root_noised = true;
+    }
+
+    /// The most visited move at the root.
+    pub fn best_move(&self) -> Option<Move> {
+        self.nodes[0]
+            .children()
+            .max_by_key(|&idx| self.nodes[idx].visits)
+            .map(|idx| self.nodes[idx].mv)
+    }
+
+    /// Picks a root move with probability proportional to `visits^(1 / temperature)`.
+    /// A temperature of zero picks the most visited move.
+    pub fn choose_move(&mut self, temperature: f32) -> Option<Move> {
+        if temperature <= 0.0 {
+            return self.best_move();
+        }
+        let children = self.nodes[0].children();
+        let max = children.clone().map(|idx| self.nodes[idx].visits).max()?;
+        if max == 0 {
+            return self.best_move();
+        }
+        // scale by the maximum before exponentiating so that low temperatures don't overflow.
+        let weights: Vec<f64> = children
+            .clone()
+            .map(|idx| (f64::from(self.nodes[idx].visits) / f64::from(max)).powf(1.0 / f64::from(temperature)))
+            .collect();
+        let mut pick = self.rng.next_f64() * weights.iter().sum::<f64>();
+        for (idx, w) in children.zip(weights) {
+            if pick < w {
+                return Some(self.nodes[idx].mv);
+            }
+            pick -= w;
+        }
+        self.best_move()
+    }
+
+    /// The value of the root position in `[-1, 1]` for the side to move.
+    pub fn root_value(&self) -> f32 {
+        let root = &self.nodes[0];
+        if root.visits == 0 {
+            0.0
+        } else {
+            -root.q()
+        }
+    }
+
+    /// The root visit distribution over the `Move::index` space.
+    pub fn visit_distribution(&self) -> Vec<f32> {
+        let mut dist = vec![0.0; POLICY_LEN];
+        let children = self.nodes[0].children();
+        let total = children.clone().map(|idx| self.nodes[idx].visits).sum::<u32>().max(1) as f32;
+        for idx in children {
+            dist[self.nodes[idx].mv.index()] = self.nodes[idx].visits as f32 / total;
+        }
+        dist
+    }
+
+    /// Plays `mv` at the root, keeping the subtree below it for the next search.
+    pub fn advance(&mut self, mv: Move) {
+        self.board.make_move(mv);
+        self.root_noised = false;
+        let new_root = self.nodes[0].children().find(|&idx| self.nodes[idx].mv == mv);
+
+        let Some(new_root) = new_root else {
+            self.nodes.clear();
+            self.nodes.push(Node::new(Move::Pass, 1.0));
+            return;
+        };
+
+        self.nodes = reroot(&self.nodes, new_root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{uniform_policy, Params, Puct, POLICY_LEN};
+    use crate::{
+        mcts::{ArenaNode, Limits},
+        rng::Rng,
+        Board, Player,
+    };
+
+    #[test]
+    #[should_panic(expected = "at least one limit")]
+    fn rejects_unbounded_search() {
+        Puct::new(Board::default(), Params::default(), uniform_policy, Rng::new(1)).search(Limits::default());
+    }
+
+    #[test]
+    fn visit_distribution_covers_legal_moves() {
+        let board = Board::default();
+        let mut search = Puct::new(board, Params::default(), uniform_policy, Rng::new(1));
+        search.search(Limits::visits(400));
+        let dist = search.visit_distribution();
+        assert_eq!(dist.len(), POLICY_LEN);
+        let mut legal = vec![false; POLICY_LEN];
+        board.generate_moves(|mv| {
+            legal[mv.index()] = true;
+            false
+        });
+        for (idx, &p) in dist.iter().enumerate() {
+            assert!(legal[idx] || p == 0.0);
+        }
+        assert!((dist.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn finds_immediate_win() {
+        let board: Board = "7/7/7/7/7/1o5/x6 x 0 1".parse().unwrap();
+        let params = Params { dirichlet_epsilon: 0.25, ..Params::default() };
+        let mut search = Puct::new(board, params, uniform_policy, Rng::new(2));
+        let best = search.search(Limits::visits(800)).unwrap();
+        let mut after = board;
+        after.make_move(best);
+        assert_eq!(after.outcome(), Some(Some(Player::White)));
+        assert!(search.root_value() > 0.9);
+    }
+
+    fn root_priors<E>(search: &Puct<E>) -> Vec<f32> {
+        search.nodes[0].children().map(|idx| search.nodes[idx].prior).collect()
+    }
+
+    #[test]
+    fn noises_a_kept_root_before_searching_it() {
+        let params = Params { dirichlet_epsilon: 0.25, ..Params::default() };
+        let mut search = Puct::new(Board::default(), params, uniform_policy, Rng::new(4));
+        let mv = search.search(Limits::visits(200)).unwrap();
+        search.advance(mv);
+        let kept = root_priors(&search);
+        assert!(!kept.is_empty());
+        // the limit is already reached, so only the noise changes the tree.
+        search.search(Limits::visits(u64::from(search.root_visits())));
+        assert_ne!(root_priors(&search), kept);
+
+        let mut fresh = Puct::new(Board::default(), params, uniform_policy, Rng::new(4));
+        fresh.search(Limits::visits(1));
+        assert!(root_priors(&fresh).windows(2).any(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn temperature_sampling_picks_visited_moves() {
+        let mut search = Puct::new(Board::default(), Params::default(), uniform_policy, Rng::new(3));
+        search.search(Limits::visits(200));
+        let dist = search.visit_distribution();
+        for _ in 0..50 {
+            let mv = search.choose_move(1.0).unwrap();
+            assert!(dist[mv.index()] > 0.0);
+        }
+        assert_eq!(search.choose_move(0.0), search.best_move());
+    }
+}
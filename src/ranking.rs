@@ -0,0 +1,451 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{Board, Player, Square, BB_ALL, FILE_H, RANK_8};
+
+/// `BINOMIAL[n][k]` is n choose k, for up to the 49 squares of the board.
+pub(crate) static BINOMIAL: [[u64; 50]; 50] = {
+    let mut table = [[0; 50]; 50];
+    let mut n = 0;
+    while n < 50 {
+        table[n][0] = 1;
+        let mut k = 1;
+        while k <= n {
+            table[n][k] = table[n - 1][k - 1] + table[n - 1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+};
+
+/// What every position ranked together shares: the walls and the number of pieces of each
+/// side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Params {
+    pub walls: u64,
+    pub white: u32,
+    pub black: u32,
+}
+
+impl Params {
+    pub fn of(board: &Board) -> Self {
+        Self { walls: board.walls(), white: board.white.count_ones(), black: board.black.count_ones() }
+    }
+
+    /// The number of positions, counting each side to move, or `None` if they do not fit in
+    /// 64 bits or the pieces do not fit on the board.
+    pub fn count(&self) -> Option<u64> {
+        let open = open_squares(self.walls).count();
+        let (white, black) = (self.white as usize, self.black as usize);
+        if white + black > open {
+            return None;
+        }
+        BINOMIAL[open][white].checked_mul(BINOMIAL[open - white][black])?.checked_mul(2)
+    }
+}
+
+/// The squares without walls, in order of `Square::compressed_index`.
+fn open_squares(walls: u64) -> impl Iterator<Item = Square> {
+    (0..49).map(Square::from_compressed_index).filter(move |sq| walls & sq.as_set() == 0)
+}
+
+/// The rank of `board` among the positions sharing its [`Params`], from 0 to one less than
+/// their count. Move counters are ignored.
+///
+/// The side to move is the lowest bit. The rest is the rank of the set of white squares
+/// among the open squares, then of the black squares among the open squares left, each in
+/// the combinatorial number system: squares `c1 < c2 < ... < ck` rank as the sum of
+/// `ci choose i`.
+///
+/// Panics if the positions sharing the board's parameters do not fit in 64 bits, that is if
+/// [`Params::count`] is `None` for them, as with 8 white and 14 black pieces on a board without
+/// walls. [`try_rank`] returns `None` instead.
+pub fn rank(board: &Board) -> u64 {
+    try_rank(board).expect("too many positions to rank in 64 bits")
+}
+
+/// The [`rank`] of `board`, or `None` if the positions sharing its parameters do not fit in
+/// 64 bits.
+pub fn try_rank(board: &Board) -> Option<u64> {
+    let params = Params::of(board);
+    params.count()?;
+    let squares: Vec<Square> = open_squares(params.walls).collect();
+    let (white, black) = (open_set(&squares, board.white), open_set(&squares, board.black));
+    let black_count = BINOMIAL[squares.len() - params.white as usize][params.black as usize];
+    let pieces = subset_rank(white, 0) * black_count + subset_rank(black, white);
+    Some(pieces * 2 + u64::from(board.turn() == Player::Black))
+}
+
+/// The position with `rank` among those sharing `params`, with its move counters at zero.
+/// `rank` must be below `params.count()`.
+pub fn unrank(rank: u64, params: &Params) -> Board {
+    debug_assert!(params.count().is_some_and(|count| rank < count));
+    let squares: Vec<Square> = open_squares(params.walls).collect();
+    let free = squares.len() - params.white as usize;
+    let black_count = BINOMIAL[free][params.black as usize];
+    let pieces = rank / 2;
+
+    let white = subset_unrank(pieces / black_count, params.white as usize, 0, squares.len());
+    let black = subset_unrank(pieces % black_count, params.black as usize, white, free);
+    Board {
+        white: board_set(&squares, white),
+        black: board_set(&squares, black),
+        walls: params.walls & BB_ALL | RANK_8 | FILE_H,
+        ply: (rank & 1) as u16,
+        halfmove: 0,
+    }
+}
+
+/// The squares of `bb` as a set of their places in `squares`.
+fn open_set(squares: &[Square], bb: u64) -> u64 {
+    squares.iter().enumerate().filter(|(_, sq)| bb & sq.as_set() != 0).fold(0, |set, (i, _)| set | 1 << i)
+}
+
+/// The squares at the places in `set`.
+fn board_set(squares: &[Square], set: u64) -> u64 {
+    squares.iter().enumerate().filter(|&(i, _)| set >> i & 1 != 0).fold(0, |bb, (_, sq)| bb | sq.as_set())
+}
+
+/// The rank of `set` in the combinatorial number system, counting only the places that are
+/// not in `excluded`.
+pub(crate) fn subset_rank(mut set: u64, excluded: u64) -> u64 {
+    let (mut rank, mut count) = (0, 0);
+    while set != 0 {
+        let i = set.trailing_zeros();
+        count += 1;
+        rank += BINOMIAL[(i - (excluded & ((1 << i) - 1)).count_ones()) as usize][count];
+        set &= set - 1;
+    }
+    rank
+}
+
+/// The set of `count` places with `rank` in the combinatorial number system, among the first
+/// `free` places that are not in `excluded`.
+pub(crate) fn subset_unrank(mut rank: u64, count: usize, excluded: u64, mut free: usize) -> u64 {
+    let mut set = 0;
+    for k in (1..=count).rev() {
+        while BINOMIAL[free][k] > rank {
+            free -= 1;
+        }
+        rank -= BINOMIAL[free][k];
+        set |= 1 << nth_unset(excluded, free);
+    }
+    set
+}
+
+/// The place of the `n`th place, from zero, that is not in `set`.
+fn nth_unset(set: u64, n: usize) -> usize {
+    let mut unset = !set;
+    for _ in 0..n {
+        unset &= unset - 1;
+    }
+    unset.trailing_zeros() as usize
+}
+
+/// The sets of `k` of the first `n` places, in increasing order.
+fn subsets(n: usize, k: usize) -> impl Iterator<Item = u64> {
+    let first = (k <= n).then(|| (1u64 << k) - 1);
+    core::iter::successors(first, move |&set| {
+        if set == 0 {
+            return None;
+        }
+        // the next set with as many places, by Gosper's hack.
+        let low = set & set.wrapping_neg();
+        let ripple = set + low;
+        let next = ripple | (set ^ ripple) >> 2 >> low.trailing_zeros();
+        (next >> n == 0).then_some(next)
+    })
+}
+
+/// The symmetries of a board with fixed walls that [`SymmetricRanking`] ranks up to, acting
+/// on sets of open squares given as their places in order of `Square::compressed_index`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Symmetries {
+    squares: Vec<Square>,
+    /// For each symmetry, starting with the identity, the image of a set looked up a byte at
+    /// a time.
+    tables: Vec<[[u64; 256]; 7]>,
+}
+
+impl Symmetries {
+    pub(crate) fn new(walls: u64) -> Self {
+        let squares: Vec<Square> = open_squares(walls).collect();
+        let tables = square_maps(&squares).iter().map(|map| byte_tables(map)).collect();
+        Self { squares, tables }
+    }
+
+    /// The open squares, in the order of their places.
+    pub(crate) fn squares(&self) -> &[Square] {
+        &self.squares
+    }
+
+    /// The number of symmetries.
+    pub(crate) fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// The image of `set` under the symmetry `sym`, from zero for the identity.
+    pub(crate) fn map(&self, sym: usize, set: u64) -> u64 {
+        let tables = &self.tables[sym];
+        set.to_le_bytes().iter().zip(tables).fold(0, |image, (&byte, table)| image | table[usize::from(byte)])
+    }
+
+    /// The open squares of `bb` as a set of their places.
+    pub(crate) fn open_set(&self, bb: u64) -> u64 {
+        open_set(&self.squares, bb)
+    }
+
+    /// The open squares at the places in `set`.
+    pub(crate) fn board_set(&self, set: u64) -> u64 {
+        board_set(&self.squares, set)
+    }
+
+    /// The sets of `count` open squares that are the smallest of their images, in increasing
+    /// order.
+    pub(crate) fn smallest_sets(&self, count: usize) -> Vec<u64> {
+        subsets(self.squares.len(), count).filter(|&set| (1..self.len()).all(|sym| self.map(sym, set) >= set)).collect()
+    }
+
+    /// The image of the position with `white` and `black` squares that has the smallest set
+    /// of white squares, and among those the smallest [`subset_rank`] of the black squares
+    /// among the rest, as that set and rank.
+    pub(crate) fn smallest_image(&self, white: u64, black: u64) -> (u64, u64) {
+        (0..self.len())
+            .map(|sym| {
+                let white = self.map(sym, white);
+                (white, subset_rank(self.map(sym, black), white))
+            })
+            .min()
+            .expect("the identity is a symmetry")
+    }
+}
+
+/// The symmetries of the smallest rectangle around `squares` that send each of them to
+/// another, as the place in `squares` of the image of each one, starting with the identity.
+fn square_maps(squares: &[Square]) -> Vec<Vec<usize>> {
+    let ranks = squares.iter().map(|sq| sq.rank());
+    let files = squares.iter().map(|sq| sq.file());
+    let (Some(low_rank), Some(low_file)) = (ranks.clone().min(), files.clone().min()) else {
+        return vec![Vec::new()];
+    };
+    let height = ranks.max().unwrap_or(low_rank) - low_rank;
+    let width = files.max().unwrap_or(low_file) - low_file;
+    (0..8)
+        .filter(|sym| sym & 4 == 0 || height == width)
+        .filter_map(|sym| {
+            squares
+                .iter()
+                .map(|sq| {
+                    let (mut rank, mut file) = (sq.rank() - low_rank, sq.file() - low_file);
+                    if sym & 1 != 0 {
+                        rank = height - rank;
+                    }
+                    if sym & 2 != 0 {
+                        file = width - file;
+                    }
+                    if sym & 4 != 0 {
+                        (rank, file) = (file, rank);
+                    }
+                    let image = Square::from_rank_file(rank + low_rank, file + low_file);
+                    squares.iter().position(|&sq| sq == image)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Tables from each byte of a set to its image under the map from each place `i` to `map[i]`.
+fn byte_tables(map: &[usize]) -> [[u64; 256]; 7] {
+    let mut tables = [[0; 256]; 7];
+    for (i, &image) in map.iter().enumerate() {
+        for (byte, entry) in tables[i / 8].iter_mut().enumerate() {
+            if byte >> (i % 8) & 1 != 0 {
+                *entry |= 1 << image;
+            }
+        }
+    }
+    tables
+}
+
+/// Dense ranks of positions up to the symmetries of their walls, so that each set of
+/// positions that are images of each other gets a single rank.
+///
+/// The symmetries are the reflections and rotations of the smallest rectangle around the open
+/// squares that send every open square to an open one, so the corner boards of
+/// [`Board::small`] get all eight, like the board without walls.
+///
+/// Positions are ranked by the image with the smallest set of white squares, by its place in
+/// the list of sets that are the smallest of their images, then by the black squares among
+/// the rest in the combinatorial number system, then by the side to move in the lowest bit.
+/// Where a symmetry maps the white squares onto themselves, only the black sets that are the
+/// smallest of their images under those symmetries are counted, and those are listed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymmetricRanking {
+    params: Params,
+    symmetries: Symmetries,
+    /// The sets of white squares that are the smallest of their images, in increasing order.
+    white_sets: Vec<u64>,
+    /// The first rank, without the side to move, of the positions with each white set,
+    /// followed by their number.
+    offsets: Vec<u64>,
+    /// For each white set that a symmetry maps onto itself, the ranks of the black sets that
+    /// are the smallest of their images, in increasing order. Empty for the other sets.
+    black_ranks: Vec<Vec<u64>>,
+}
+
+impl SymmetricRanking {
+    /// Panics if the positions sharing `params` do not fit in 64 bits.
+    pub fn new(params: Params) -> Self {
+        params.count().expect("too many positions to rank in 64 bits");
+        let symmetries = Symmetries::new(params.walls);
+        let free = symmetries.squares().len() - params.white as usize;
+        let black_count = BINOMIAL[free][params.black as usize];
+        let white_sets = symmetries.smallest_sets(params.white as usize);
+        let mut offsets = vec![0];
+        let mut black_ranks = Vec::with_capacity(white_sets.len());
+        for &white in &white_sets {
+            let fixing: Vec<usize> = (1..symmetries.len()).filter(|&sym| symmetries.map(sym, white) == white).collect();
+            let ranks: Vec<u64> = if fixing.is_empty() {
+                Vec::new()
+            } else {
+                (0..black_count)
+                    .filter(|&rank| {
+                        let black = subset_unrank(rank, params.black as usize, white, free);
+                        fixing.iter().all(|&sym| subset_rank(symmetries.map(sym, black), white) >= rank)
+                    })
+                    .collect()
+            };
+            let len = if fixing.is_empty() { black_count } else { ranks.len() as u64 };
+            offsets.push(offsets[offsets.len() - 1] + len);
+            black_ranks.push(ranks);
+        }
+        Self { params, symmetries, white_sets, offsets, black_ranks }
+    }
+
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+
+    /// The number of positions up to symmetry.
+    pub fn len(&self) -> u64 {
+        self.offsets[self.offsets.len() - 1] * 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The rank of `board` and its images, or `None` if it has other parameters.
+    pub fn rank(&self, board: &Board) -> Option<u64> {
+        if Params::of(board) != self.params {
+            return None;
+        }
+        let sym = &self.symmetries;
+        let (white, black_rank) = sym.smallest_image(sym.open_set(board.white), sym.open_set(board.black));
+        let i = self.white_sets.binary_search(&white).ok()?;
+        let ranks = &self.black_ranks[i];
+        let black = if ranks.is_empty() { black_rank } else { ranks.binary_search(&black_rank).ok()? as u64 };
+        Some((self.offsets[i] + black) * 2 + u64::from(board.turn() == Player::Black))
+    }
+
+    /// The representative of the positions with `rank`, which must be below `len()`.
+    pub fn unrank(&self, rank: u64) -> Board {
+        let pieces = rank / 2;
+        let i = self.offsets.partition_point(|&offset| offset <= pieces) - 1;
+        let white = self.white_sets[i];
+        let ranks = &self.black_ranks[i];
+        let black = pieces - self.offsets[i];
+        let black_rank = if ranks.is_empty() { black } else { ranks[black as usize] };
+        let free = self.symmetries.squares().len() - self.params.white as usize;
+        let black = subset_unrank(black_rank, self.params.black as usize, white, free);
+        Board {
+            white: self.symmetries.board_set(white),
+            black: self.symmetries.board_set(black),
+            walls: self.params.walls & BB_ALL | RANK_8 | FILE_H,
+            ply: (rank & 1) as u16,
+            halfmove: 0,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{rank, try_rank, unrank, Params, SymmetricRanking};
+    use crate::{retro::Index, Board, BB_ALL, FILE_H, RANK_8};
+
+    #[test]
+    fn ranks_round_trip() {
+        for size in 2..=4 {
+            let walls = Board::small(size).walls();
+            let open = (BB_ALL & !walls).count_ones();
+            let mut total = 0;
+            for white in 0..=open {
+                for black in 0..=open - white {
+                    let params = Params { walls, white, black };
+                    let count = params.count().unwrap();
+                    total += count;
+                    // 4x4 boards have too many positions to go through them all.
+                    for r in (0..count).step_by(if size < 4 { 1 } else { 997 }) {
+                        let board = unrank(r, &params);
+                        assert_eq!(Params::of(&board), params);
+                        assert_eq!(rank(&board), r, "{}", board.fen());
+                    }
+                }
+            }
+            assert_eq!(total, Index::new(walls).unwrap().len());
+        }
+
+        let board = Board::new();
+        let params = Params::of(&board);
+        assert_eq!(unrank(rank(&board), &params), board);
+        assert_eq!(Params { white: 25, black: 25, ..params }.count(), None);
+        assert_eq!(Params { white: 16, black: 16, ..params }.count(), None);
+
+        let crowded: Board = "xxxxxxx/x6/ooooooo/ooooooo/7/7/7 x 0 1".parse().unwrap();
+        assert_eq!(Params::of(&crowded), Params { walls: 0, white: 8, black: 14 });
+        assert_eq!(try_rank(&crowded), None);
+        assert_eq!(try_rank(&board), Some(rank(&board)));
+    }
+
+    #[test]
+    fn ranks_up_to_symmetry() {
+        // a 3x3 board in the middle has all eight symmetries of the board, and one in the
+        // corner the same ones about its own middle, so it is checked moved to the middle.
+        let middle = "-------/-------/--3--/--3--/--3--/-------/------- x 0 1".parse::<Board>().unwrap().walls();
+        for (walls, shift) in [(middle, 0), (Board::small(3).walls(), 18)] {
+            let centred = |board: Board| Board {
+                white: board.white << shift,
+                black: board.black << shift,
+                walls: middle | RANK_8 | FILE_H,
+                ..board
+            };
+            for (white, black) in [(1, 1), (2, 3), (4, 4), (0, 5)] {
+                let params = Params { walls, white, black };
+                let ranking = SymmetricRanking::new(params);
+                let mut keys = HashSet::new();
+                for r in 0..params.count().unwrap() {
+                    let board = unrank(r, &params);
+                    keys.insert(centred(board).canonical_key());
+                    let symmetric = ranking.rank(&board).unwrap();
+                    assert_eq!(centred(ranking.unrank(symmetric)).canonical_key(), centred(board).canonical_key());
+                }
+                assert_eq!(ranking.len(), keys.len() as u64);
+                for r in 0..ranking.len() {
+                    assert_eq!(ranking.rank(&ranking.unrank(r)), Some(r));
+                }
+            }
+        }
+        let ranking = SymmetricRanking::new(Params { walls: middle, white: 1, black: 0 });
+        assert_eq!(ranking.len(), 2 * 3);
+        assert_eq!(ranking.rank(&Board::new()), None);
+
+        let params = Params { walls: Board::small(4).walls(), white: 4, black: 4 };
+        let ranking = SymmetricRanking::new(params);
+        assert!(ranking.len() * 7 < params.count().unwrap());
+        for r in (0..ranking.len()).step_by(101) {
+            assert_eq!(ranking.rank(&ranking.unrank(r)), Some(r));
+        }
+    }
+}
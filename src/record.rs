@@ -0,0 +1,447 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, BufRead, Read, Write},
+    str::FromStr,
+};
+
+use crate::{Board, FenError, Player, BB_ALL, FILE_H, MAX_FULLMOVE, RANK_8};
+
+/// The size in bytes of an encoded [`PositionRecord`].
+pub const RECORD_SIZE: usize = 24;
+
+/// A position from a dataset, with its search score and the result of the game it came from.
+///
+/// Records encode to a fixed [`RECORD_SIZE`]-byte little-endian layout, so files of them can
+/// be memory-mapped and indexed directly:
+///
+/// | bytes  | contents                                                                         |
+/// |--------|----------------------------------------------------------------------------------|
+/// | 0..19  | a 152-bit little-endian integer: white (bits 0..49), black (49..98),             |
+/// |        | walls (98..147), side to move (147, set for black), result (148..150),           |
+/// |        | score present (150)                                                              |
+/// | 19     | halfmove clock                                                                   |
+/// | 20..22 | fullmove number, u16                                                             |
+/// | 22..24 | score, i16 (zero if absent)                                                      |
+///
+/// Bitboards are stored in the 49-square compressed layout of `Square::compressed_index`.
+/// The result is 0 if unknown, 1 for a white win, 2 for a black win and 3 for a draw.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PositionRecord {
+    pub board: Board,
+    /// The search score, from the side to move's perspective.
+    pub score: Option<i16>,
+    /// The final outcome of the game, in the form returned by `Board::outcome`.
+    pub result: Option<Option<Player>>,
+}
+
+#[derive(Debug)]
+pub enum RecordError {
+    OverlappingPieces,
+    InvalidResult,
+    ReservedBits,
+    InvalidFullmove,
+    InvalidFen(FenError),
+    InvalidScore,
+    MissingField,
+}
+
+impl Display for RecordError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::OverlappingPieces => write!(f, "Record has overlapping pieces or walls"),
+            RecordError::InvalidResult => write!(f, "Invalid result in record"),
+            RecordError::ReservedBits => write!(f, "Record has reserved bits set"),
+            RecordError::InvalidFullmove => write!(f, "Invalid fullmove number in record"),
+            RecordError::InvalidFen(e) => write!(f, "Invalid FEN in record: {e}"),
+            RecordError::InvalidScore => write!(f, "Invalid score in record"),
+            RecordError::MissingField => write!(f, "Record is missing a field"),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RecordError::InvalidFen(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Squeezes the 7x7 playing area of a bitboard into its low 49 bits.
+const fn compress(bb: u64) -> u64 {
+    let mut out = 0;
+    let mut rank = 0;
+    while rank < 7 {
+        out |= (bb >> (rank * 8) & 0x7F) << (rank * 7);
+        rank += 1;
+    }
+    out
+}
+
+const fn decompress(bits: u64) -> u64 {
+    let mut out = 0;
+    let mut rank = 0;
+    while rank < 7 {
+        out |= (bits >> (rank * 7) & 0x7F) << (rank * 8);
+        rank += 1;
+    }
+    out
+}
+
+impl PositionRecord {
+    pub fn new(board: Board) -> Self {
+        Self { board, score: None, result: None }
+    }
+
+    pub fn to_bytes(&self) -> [u8; RECORD_SIZE] {
+        let board = &self.board;
+        let white = u128::from(compress(board.white));
+        let black = u128::from(compress(board.black));
+        let walls = compress(board.walls & BB_ALL);
+        let lo = white | black << 49 | u128::from(walls) << 98;
+        let result = match self.result {
+            None => 0,
+            Some(Some(Player::White)) => 1,
+            Some(Some(Player::Black)) => 2,
+            Some(None) => 3,
+        };
+        let hi = (walls >> 30) as u32
+            | u32::from(board.turn() == Player::Black) << 19
+            | result << 20
+            | u32::from(self.score.is_some()) << 22;
+
+        let mut bytes = [0; RECORD_SIZE];
+        bytes[0..16].copy_from_slice(&lo.to_le_bytes());
+        bytes[16..19].copy_from_slice(&hi.to_le_bytes()[0..3]);
+        bytes[19] = board.halfmove;
+        bytes[20..22].copy_from_slice(&(board.ply / 2 + 1).to_le_bytes());
+        bytes[22..24].copy_from_slice(&self.score.unwrap_or(0).to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; RECORD_SIZE]) -> Result<Self, RecordError> {
+        let mut lo = [0; 16];
+        lo.copy_from_slice(&bytes[0..16]);
+        let lo = u128::from_le_bytes(lo);
+        let hi = u32::from_le_bytes([bytes[16], bytes[17], bytes[18], 0]);
+
+        const MASK_49: u128 = (1 << 49) - 1;
+        let white = decompress((lo & MASK_49) as u64);
+        let black = decompress((lo >> 49 & MASK_49) as u64);
+        let walls = decompress((lo >> 98) as u64 | u64::from(hi & 0x7FFFF) << 30);
+        if white & black != 0 || (white | black) & walls != 0 {
+            return Err(RecordError::OverlappingPieces);
+        }
+
+        let black_to_move = hi >> 19 & 1 != 0;
+        let result = match hi >> 20 & 0b11 {
+            0 => None,
+            1 => Some(Some(Player::White)),
+            2 => Some(Some(Player::Black)),
+            _ => Some(None),
+        };
+        if hi >> 23 != 0 {
+            return Err(RecordError::ReservedBits);
+        }
+        let has_score = hi >> 22 & 1 != 0;
+
+        let fullmove = u16::from_le_bytes([bytes[20], bytes[21]]);
+        if !(1..=MAX_FULLMOVE).contains(&fullmove) {
+            return Err(RecordError::InvalidFullmove);
+        }
+        let score = i16::from_le_bytes([bytes[22], bytes[23]]);
+
+        Ok(Self {
+            board: Board {
+                white,
+                black,
+                walls: walls | RANK_8 | FILE_H,
+                ply: (fullmove - 1) * 2 + u16::from(black_to_move),
+                halfmove: bytes[19],
+            },
+            score: has_score.then_some(score),
+            result,
+        })
+    }
+}
+
+/// The text form of a record, one per line: `<fen> | <score> | <result>`, where the score is
+/// from the side to move's perspective and the result is `1.0`, `0.5` or `0.0` for white.
+/// Either may be `-` if unknown.
+impl Display for PositionRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.board.write_fen(f)?;
+        write!(f, " | ")?;
+        match self.score {
+            Some(score) => write!(f, "{score} | ")?,
+            None => write!(f, "- | ")?,
+        }
+        match self.result {
+            None => write!(f, "-"),
+            Some(Some(Player::White)) => write!(f, "1.0"),
+            Some(Some(Player::Black)) => write!(f, "0.0"),
+            Some(None) => write!(f, "0.5"),
+        }
+    }
+}
+
+impl FromStr for PositionRecord {
+    type Err = RecordError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split('|').map(str::trim);
+        let board = fields.next().ok_or(RecordError::MissingField)?.parse().map_err(RecordError::InvalidFen)?;
+        let score = match fields.next().ok_or(RecordError::MissingField)? {
+            "-" => None,
+            score => Some(score.parse().map_err(|_| RecordError::InvalidScore)?),
+        };
+        let result = match fields.next().ok_or(RecordError::MissingField)? {
+            "-" => None,
+            "1.0" | "1" => Some(Some(Player::White)),
+            "0.0" | "0" => Some(Some(Player::Black)),
+            "0.5" => Some(None),
+            _ => return Err(RecordError::InvalidResult),
+        };
+        Ok(Self { board, score, result })
+    }
+}
+
+/// Reads a stream of [`PositionRecord`]s.
+pub struct RecordReader<R> {
+    inner: R,
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    /// Reads the next record, returning `Ok(None)` at a clean end of stream.
+    pub fn read_record(&mut self) -> io::Result<Option<PositionRecord>> {
+        let mut bytes = [0; RECORD_SIZE];
+        let mut filled = 0;
+        while filled < RECORD_SIZE {
+            match self.inner.read(&mut bytes[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated position record")),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        PositionRecord::from_bytes(&bytes)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Iterator for RecordReader<R> {
+    type Item = io::Result<PositionRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Writes a stream of [`PositionRecord`]s.
+pub struct RecordWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> RecordWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    pub fn write_record(&mut self, record: &PositionRecord) -> io::Result<()> {
+        self.inner.write_all(&record.to_bytes())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// The on-disk formats of a dataset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// One record per line, in the text form of `PositionRecord`'s `Display`.
+    Text,
+    /// Fixed-size binary records.
+    Binary,
+}
+
+impl FromStr for Format {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "binary" => Ok(Format::Binary),
+            _ => Err("unknown dataset format"),
+        }
+    }
+}
+
+enum ReaderInner<R> {
+    Text(io::Lines<R>),
+    Binary(RecordReader<R>),
+}
+
+/// Reads [`PositionRecord`]s from a dataset in either [`Format`].
+pub struct DatasetReader<R> {
+    inner: ReaderInner<R>,
+}
+
+impl<R: BufRead> DatasetReader<R> {
+    pub fn new(inner: R, format: Format) -> Self {
+        let inner = match format {
+            Format::Text => ReaderInner::Text(inner.lines()),
+            Format::Binary => ReaderInner::Binary(RecordReader::new(inner)),
+        };
+        Self { inner }
+    }
+
+    /// Reads the next record, returning `Ok(None)` at the end of the dataset. Blank lines in
+    /// text datasets are skipped.
+    pub fn read_record(&mut self) -> io::Result<Option<PositionRecord>> {
+        match &mut self.inner {
+            ReaderInner::Text(lines) => loop {
+                match lines.next().transpose()? {
+                    None => return Ok(None),
+                    Some(line) if line.trim().is_empty() => {}
+                    Some(line) => {
+                        return line
+                            .parse()
+                            .map(Some)
+                            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{e}: {line}")))
+                    }
+                }
+            },
+            ReaderInner::Binary(reader) => reader.read_record(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for DatasetReader<R> {
+    type Item = io::Result<PositionRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Writes [`PositionRecord`]s to a dataset in either [`Format`].
+pub struct DatasetWriter<W: Write> {
+    inner: W,
+    format: Format,
+}
+
+impl<W: Write> DatasetWriter<W> {
+    pub fn new(inner: W, format: Format) -> Self {
+        Self { inner, format }
+    }
+
+    pub fn write_record(&mut self, record: &PositionRecord) -> io::Result<()> {
+        match self.format {
+            Format::Text => writeln!(self.inner, "{record}"),
+            Format::Binary => self.inner.write_all(&record.to_bytes()),
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PositionRecord, RecordError, RecordReader, RecordWriter, RECORD_SIZE};
+    use crate::{rng::Rng, Board, Player};
+
+    #[test]
+    fn bytes_roundtrip() {
+        let mut rng = Rng::new(7);
+        let walls: Board = "x2-2o/7/2-1-2/3-3/2-1-2/7/o2-2x x 0 1".parse().unwrap();
+        for start in [Board::default(), walls] {
+            let mut board = start;
+            while !board.game_over() {
+                let record = PositionRecord {
+                    board,
+                    score: (rng.range(0, 2) == 0).then(|| rng.next_u64() as i16),
+                    result: [None, Some(None), Some(Some(Player::White)), Some(Some(Player::Black))][rng.range(0, 4)],
+                };
+                assert_eq!(PositionRecord::from_bytes(&record.to_bytes()).unwrap(), record);
+                assert_eq!(record.to_string().parse::<PositionRecord>().unwrap(), record);
+                board.make_random_move(|lo, hi| rng.range(lo, hi));
+            }
+        }
+    }
+
+    #[test]
+    fn bytes_roundtrip_at_move_limit() {
+        for fen in ["x5o/7/7/7/7/7/o5x x 0 32767", "x5o/7/7/7/7/7/o5x o 0 32767"] {
+            let record = PositionRecord::new(fen.parse().unwrap());
+            let mut decoded = PositionRecord::from_bytes(&record.to_bytes()).unwrap();
+            assert_eq!(decoded, record);
+            decoded.board.make_random_move(|lo, _| lo);
+        }
+        let mut bytes = PositionRecord::new(Board::default()).to_bytes();
+        bytes[20..22].copy_from_slice(&32768u16.to_le_bytes());
+        assert!(matches!(PositionRecord::from_bytes(&bytes), Err(RecordError::InvalidFullmove)));
+    }
+
+    #[test]
+    fn rejects_reserved_bits() {
+        let mut bytes = PositionRecord::new(Board::default()).to_bytes();
+        bytes[18] |= 0x80;
+        assert!(matches!(PositionRecord::from_bytes(&bytes), Err(RecordError::ReservedBits)));
+    }
+
+    #[test]
+    fn stable_layout() {
+        let board: Board = "x5o/7/7/3-3/7/7/o5x o 12 34".parse().unwrap();
+        let record = PositionRecord { board, score: Some(-2), result: Some(Some(Player::Black)) };
+        let bytes = record.to_bytes();
+        // white a7, g1; black a1, g7; wall d4
+        let expected_lo: u128 = 1 << 42 | 1 << 6 | (1 | 1 << 48) << 49 | 1 << (98 + 24);
+        assert_eq!(bytes[0..16], expected_lo.to_le_bytes());
+        assert_eq!(bytes[16..19], [0b0000_0000, 0b0000_0000, 0b0110_1000]);
+        assert_eq!(bytes[19..24], [12, 34, 0, 0xFE, 0xFF]);
+    }
+
+    #[test]
+    fn stream_roundtrip() {
+        let mut board = Board::default();
+        let mut records = Vec::new();
+        let mut writer = RecordWriter::new(Vec::new());
+        for i in 0..20 {
+            let record = PositionRecord { board, score: Some(i), result: None };
+            writer.write_record(&record).unwrap();
+            records.push(record);
+            board.make_random_move(|lo, _| lo);
+        }
+        let bytes = writer.into_inner();
+        assert_eq!(bytes.len(), 20 * RECORD_SIZE);
+        let read = RecordReader::new(bytes.as_slice()).collect::<std::io::Result<Vec<_>>>().unwrap();
+        assert_eq!(read, records);
+        assert!(RecordReader::new(&bytes[..RECORD_SIZE + 3]).nth(1).unwrap().is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    record::{DatasetReader, DatasetWriter, Format, PositionRecord},
+    search::{for_each_split, Limits, Search},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Options {
+    pub format: Format,
+    /// The limits of the search run on each position, usually a fixed depth or node count.
+    pub limits: Limits,
+    pub threads: usize,
+    pub tt_mb: usize,
+    /// The number of positions read, scored and written between checkpoints.
+    pub batch_size: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self { format: Format::Text, limits: Limits::depth(6), threads: 1, tt_mb: 16, batch_size: 16_384 }
+    }
+}
+
+/// How far a rescoring job has got: the number of records written and the length of the
+/// output file at that point.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub records: u64,
+    pub output_len: u64,
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("malformed checkpoint {}", path.display()));
+        let mut checkpoint = Self::default();
+        for line in text.lines() {
+            match line.split_once(' ') {
+                Some(("records", n)) => checkpoint.records = n.parse().map_err(|_| invalid())?,
+                Some(("output_len", n)) => checkpoint.output_len = n.parse().map_err(|_| invalid())?,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Some(checkpoint))
+    }
+
+    /// Saves the checkpoint, replacing the old one atomically so that a crash can't corrupt it.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut tmp = PathBuf::from(path);
+        tmp.as_mut_os_string().push(".tmp");
+        fs::write(&tmp, format!("records {}\noutput_len {}\n", self.records, self.output_len))?;
+        fs::rename(tmp, path)
+    }
+}
+
+/// Re-scores `records` in place, splitting them between the given searchers, one thread each.
+///
+/// Each searcher is cleared before every position, so that a score does not depend on what
+/// was searched before it and the same records always get the same scores.
+pub fn rescore_records(records: &mut [PositionRecord], searches: &mut [Search], limits: Limits) {
+    for_each_split(records, searches, |search, record| {
+        search.clear();
+        let result = search.search(&record.board, limits);
+        record.score = Some(result.score.clamp(i32::from(i16::MIN), i32::from(i16::MAX)) as i16);
+    });
+}
+
+/// Re-scores every position in `input`, writing the records in the same order to `output`.
+///
+/// Progress is saved to `checkpoint` after every batch. If `checkpoint` exists when this is
+/// called, the job resumes from it: the output is truncated to the checkpointed length and
+/// the positions already written are skipped. The checkpoint is removed once the job is done.
+///
+/// Returns the total number of records written, and calls `progress` with it after each batch.
+pub fn rescore_file(
+    input: &Path,
+    output: &Path,
+    checkpoint: &Path,
+    options: &Options,
+    mut progress: impl FnMut(u64),
+) -> io::Result<u64> {
+    let resume = Checkpoint::load(checkpoint)?.unwrap_or_default();
+
+    let mut source = DatasetReader::new(BufReader::new(File::open(input)?), options.format);
+    for _ in 0..resume.records {
+        if source.read_record()?.is_none() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "checkpoint is past the end of the input"));
+        }
+    }
+
+    let mut file = OpenOptions::new().write(true).create(true).truncate(false).open(output)?;
+    file.set_len(resume.output_len)?;
+    file.seek(SeekFrom::End(0))?;
+    let mut writer = DatasetWriter::new(BufWriter::new(file), options.format);
+
+    let mut searches: Vec<Search> = (0..options.threads.max(1)).map(|_| Search::new(options.tt_mb)).collect();
+    let mut done = resume;
+    let mut batch = Vec::with_capacity(options.batch_size);
+    loop {
+        batch.clear();
+        while batch.len() < options.batch_size.max(1) {
+            match source.read_record()? {
+                Some(record) => batch.push(record),
+                None => break,
+            }
+        }
+        if batch.is_empty() {
+            break;
+        }
+
+        rescore_records(&mut batch, &mut searches, options.limits);
+
+        for record in &batch {
+            writer.write_record(record)?;
+        }
+        writer.flush()?;
+        writer.get_ref().get_ref().sync_data()?;
+
+        done.records += batch.len() as u64;
+        done.output_len = writer.get_ref().get_ref().metadata()?.len();
+        done.save(checkpoint)?;
+        progress(done.records);
+    }
+
+    match fs::remove_file(checkpoint) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    Ok(done.records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rescore_file, Checkpoint, Options};
+    use crate::{
+        record::{Format, PositionRecord, RecordReader, RecordWriter, RECORD_SIZE},
+        search::Limits,
+        Board,
+    };
+
+    fn positions() -> Vec<PositionRecord> {
+        let mut board = Board::default();
+        let mut records = Vec::new();
+        for i in 0..30 {
+            records.push(PositionRecord { board, score: None, result: Some(None) });
+            board.make_random_move(|lo, hi| (lo + i * 7) % hi);
+        }
+        records
+    }
+
+    #[test]
+    fn resumes_from_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("ataxxgen-rescore-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (input, output, checkpoint) = (dir.join("in.bin"), dir.join("out.bin"), dir.join("ckpt"));
+
+        let mut writer = RecordWriter::new(std::fs::File::create(&input).unwrap());
+        for record in positions() {
+            writer.write_record(&record).unwrap();
+        }
+        drop(writer);
+
+        let options = Options { format: Format::Binary, limits: Limits::depth(2), threads: 3, tt_mb: 1, batch_size: 8 };
+        let full = rescore_file(&input, &output, &checkpoint, &options, |_| {}).unwrap();
+        assert_eq!(full, 30);
+        assert!(!checkpoint.exists());
+        let expected = std::fs::read(&output).unwrap();
+
+        // pretend a job died after two batches, having written part of a third.
+        std::fs::write(&output, &expected[..16 * RECORD_SIZE + 5]).unwrap();
+        Checkpoint { records: 16, output_len: 16 * RECORD_SIZE as u64 }.save(&checkpoint).unwrap();
+        let mut batches = Vec::new();
+        rescore_file(&input, &output, &checkpoint, &options, |n| batches.push(n)).unwrap();
+        assert_eq!(batches, [24, 30]);
+        let resumed = std::fs::read(&output).unwrap();
+        assert_eq!(resumed, expected);
+
+        // nor do the scores depend on how the work is split.
+        let options = Options { threads: 1, batch_size: 5, ..options };
+        rescore_file(&input, &output, &checkpoint, &options, |_| {}).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), expected);
+
+        let records = RecordReader::new(resumed.as_slice()).collect::<std::io::Result<Vec<_>>>().unwrap();
+        assert_eq!(records.len(), 30);
+        for (rescored, original) in records.iter().zip(positions()) {
+            assert_eq!(rescored.board, original.board);
+            assert_eq!(rescored.result, original.result);
+            assert!(rescored.score.is_some());
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,694 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+
+use crate::{
+    ranking::{subset_unrank, Symmetries, BINOMIAL},
+    Board, Player, Square, BB_ALL, FILE_H, RANK_8,
+};
+
+/// The most open squares an [`Index`] can cover, as on a 4x4 board.
+///
+/// Generation keeps a byte for each of the `2 * 3^n` indices and lists each layer's indices in
+/// full, so a 5x5 board would need over a terabyte. Boards up to 5x5 are solved with a
+/// [`SymmetricIndex`] and [`generate_layers`] instead.
+pub const MAX_SQUARES: usize = 16;
+
+/// The most open squares a [`SymmetricIndex`] can cover, as on a 5x5 board.
+pub const MAX_LAYERED_SQUARES: usize = 25;
+
+/// The start of every table file, followed by the walls as a little-endian `u64`.
+const MAGIC: &[u8; 8] = b"ATXWDL01";
+
+/// The start of every layer file, followed by the walls and the number of pieces of the
+/// layer as little-endian `u64`s.
+const LAYER_MAGIC: &[u8; 8] = b"ATXWDL02";
+const LAYER_HEADER_LEN: u64 = 24;
+
+// the values of positions, two bits each. Only generation leaves positions unknown.
+const UNKNOWN: u8 = 0;
+const WIN: u8 = 1;
+const DRAW: u8 = 2;
+const LOSS: u8 = 3;
+
+/// The result of a position under perfect play, from the side to move's perspective.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wdl {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl Wdl {
+    fn from_code(code: u8) -> Self {
+        match code {
+            WIN => Wdl::Win,
+            LOSS => Wdl::Loss,
+            _ => Wdl::Draw,
+        }
+    }
+}
+
+/// Numbers every position of a board with fixed walls: the side to move in the lowest bit,
+/// then each open square as a base-3 digit, 0 when empty, 1 for white and 2 for black.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Index {
+    walls: u64,
+    squares: Vec<Square>,
+}
+
+impl Index {
+    /// The index of the boards with `walls`, or `None` if they leave more than
+    /// [`MAX_SQUARES`] squares open.
+    pub fn new(walls: u64) -> Option<Self> {
+        let walls = walls & BB_ALL;
+        let squares: Vec<Square> = Square::all().filter(|sq| walls & sq.as_set() == 0).collect();
+        (squares.len() <= MAX_SQUARES).then_some(Self { walls, squares })
+    }
+
+    pub fn walls(&self) -> u64 {
+        self.walls
+    }
+
+    /// The number of indices, `2 * 3^n` for `n` open squares, including positions that
+    /// cannot be reached in a game.
+    pub fn len(&self) -> u64 {
+        2 * 3u64.pow(self.squares.len() as u32)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The index of `board`, or `None` if its walls are not the ones of this index.
+    /// Move counters are ignored.
+    pub fn index_of(&self, board: &Board) -> Option<u64> {
+        (board.walls & BB_ALL == self.walls).then(|| self.position_index(board))
+    }
+
+    /// The position with `index`, with its move counters at zero.
+    pub fn board_at(&self, index: u64) -> Board {
+        let mut board =
+            Board { white: 0, black: 0, walls: self.walls | RANK_8 | FILE_H, ply: (index & 1) as u16, halfmove: 0 };
+        let mut digits = index >> 1;
+        for sq in &self.squares {
+            match digits % 3 {
+                1 => board.white |= sq.as_set(),
+                2 => board.black |= sq.as_set(),
+                _ => {}
+            }
+            digits /= 3;
+        }
+        board
+    }
+
+    fn position_index(&self, board: &Board) -> u64 {
+        let digits = self.squares.iter().rev().fold(0, |digits, &sq| {
+            digits * 3
+                + match board.player_at(sq) {
+                    None => 0,
+                    Some(Player::White) => 1,
+                    Some(Player::Black) => 2,
+                }
+        });
+        digits * 2 + u64::from(board.turn() == Player::Black)
+    }
+
+    /// The indices of every position with `occupied` pieces on the board.
+    fn layer(&self, occupied: u32) -> Vec<u64> {
+        let powers: Vec<u64> = (0..self.squares.len() as u32).map(|i| 3u64.pow(i)).collect();
+        let digits = |mut set: u32, digit: u64| {
+            let mut sum = 0;
+            while set != 0 {
+                sum += digit * powers[set.trailing_zeros() as usize];
+                set &= set - 1;
+            }
+            sum
+        };
+        let mut layer = Vec::new();
+        for pieces in (0..1u32 << self.squares.len()).filter(|pieces| pieces.count_ones() == occupied) {
+            // every subset of the pieces is white once.
+            let mut white = pieces;
+            loop {
+                let position = digits(white, 1) + digits(pieces & !white, 2);
+                layer.extend([position * 2, position * 2 + 1]);
+                if white == 0 {
+                    break;
+                }
+                white = (white - 1) & pieces;
+            }
+        }
+        layer
+    }
+}
+
+/// A win/draw/loss table of every position of a board with fixed walls, such as the smaller
+/// boards of [`Board::small`], made by retrograde analysis.
+///
+/// The table covers the game without the 50-move rule: positions that neither side can win
+/// however long they play are draws. Probing ignores the move counters, so results near the
+/// end of the move limit may differ from the game as it is played.
+///
+/// Tables take a byte per position while they are generated and two bits on disk, so the 86
+/// million positions of a 4x4 board, the largest that can be indexed, take 22 MB. Larger
+/// boards are solved to disk by [`generate_layers`] and probed with a [`LayeredTable`].
+pub struct Table {
+    index: Index,
+    /// Four values per byte, lowest bits first.
+    values: Vec<u8>,
+}
+
+impl Table {
+    /// Solves every position of `index` on `threads` threads.
+    ///
+    /// Singles fill a square and nothing empties one, so positions are solved in layers from
+    /// the full board down, each one only needing itself and the layers before it. Within
+    /// a layer, positions are revisited until none changes: a position is won if a move leads
+    /// to a lost one and lost if every move leads to a won one, and what is left is drawn.
+    pub fn generate(index: Index, threads: usize) -> Self {
+        let values: Vec<AtomicU8> = (0..index.len()).map(|_| AtomicU8::new(UNKNOWN)).collect();
+        for occupied in (0..=index.squares.len() as u32).rev() {
+            let mut pending = index.layer(occupied);
+            loop {
+                let changed = AtomicBool::new(false);
+                let chunk_size = pending.len().div_ceil(threads.max(1)).max(1);
+                std::thread::scope(|s| {
+                    for chunk in pending.chunks(chunk_size) {
+                        let (index, values, changed) = (&index, &values, &changed);
+                        s.spawn(move || {
+                            for &i in chunk {
+                                let value = resolve(&index.board_at(i), |child| {
+                                    values[index.position_index(child) as usize].load(Ordering::Relaxed)
+                                });
+                                if value != UNKNOWN {
+                                    values[i as usize].store(value, Ordering::Relaxed);
+                                    changed.store(true, Ordering::Relaxed);
+                                }
+                            }
+                        });
+                    }
+                });
+                pending.retain(|&i| values[i as usize].load(Ordering::Relaxed) == UNKNOWN);
+                if !changed.into_inner() {
+                    break;
+                }
+            }
+            for i in pending {
+                values[i as usize].store(DRAW, Ordering::Relaxed);
+            }
+        }
+
+        let mut packed = vec![0; packed_len(index.len())];
+        for (i, value) in values.into_iter().enumerate() {
+            packed[i / 4] |= value.into_inner() << (i % 4 * 2);
+        }
+        Self { index, values: packed }
+    }
+
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// The value of the position with `index`.
+    pub fn get(&self, index: u64) -> Wdl {
+        let index = index as usize;
+        Wdl::from_code(self.values[index / 4] >> (index % 4 * 2) & 3)
+    }
+
+    /// The value of `board`, or `None` if the table is for other walls.
+    pub fn probe(&self, board: &Board) -> Option<Wdl> {
+        self.index.index_of(board).map(|index| self.get(index))
+    }
+
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&self.index.walls.to_le_bytes())?;
+        out.write_all(&self.values)?;
+        out.flush()
+    }
+
+    pub fn read(mut input: impl Read) -> io::Result<Self> {
+        let mut header = [0; 16];
+        input.read_exact(&mut header)?;
+        if header[..8] != MAGIC[..] {
+            return Err(invalid_data("not a WDL table"));
+        }
+        let walls = u64::from_le_bytes(header[8..].try_into().expect("eight bytes"));
+        let index = Index::new(walls).ok_or_else(|| invalid_data("too many open squares"))?;
+        let mut values = Vec::new();
+        input.read_to_end(&mut values)?;
+        if values.len() != packed_len(index.len()) {
+            return Err(invalid_data("wrong table size"));
+        }
+        Ok(Self { index, values })
+    }
+}
+
+fn packed_len(positions: u64) -> usize {
+    positions.div_ceil(4) as usize
+}
+
+/// The value of `board` given what `value_of` knows of the positions it leads to, or
+/// `UNKNOWN` if that is not enough yet.
+fn resolve(board: &Board, value_of: impl Fn(&Board) -> u8) -> u8 {
+    if let Some(outcome) = board.outcome() {
+        return match outcome {
+            None => DRAW,
+            Some(winner) if winner == board.turn() => WIN,
+            Some(_) => LOSS,
+        };
+    }
+    let (mut win, mut all_lost) = (false, true);
+    board.generate_moves(|mv| {
+        let mut child = *board;
+        child.make_move(mv);
+        match value_of(&child) {
+            LOSS => win = true,
+            WIN => {}
+            _ => all_lost = false,
+        }
+        win
+    });
+    if win {
+        WIN
+    } else if all_lost {
+        LOSS
+    } else {
+        UNKNOWN
+    }
+}
+
+/// Numbers the positions of a board with fixed walls up to its symmetries, in a layer for
+/// each number of pieces on the board, for boards too big for an [`Index`].
+///
+/// Within a layer, positions are grouped by their number of white pieces, and a group numbers
+/// them like a [`SymmetricRanking`](crate::ranking::SymmetricRanking), with the same
+/// symmetries. To avoid listing black squares, a set of white squares that a symmetry maps
+/// onto itself keeps every black set, which leaves several numbers for images of the same
+/// position. Those are solved like the rest, but only the one with the smallest black rank is
+/// probed.
+#[derive(Clone, Debug)]
+pub struct SymmetricIndex {
+    walls: u64,
+    symmetries: Symmetries,
+    /// For each number of white pieces, the sets of white squares that are the smallest of
+    /// their images, in increasing order.
+    white_sets: Vec<Vec<u64>>,
+    /// `offsets[n][w]` is the first index of the positions of layer `n` with `w` white
+    /// pieces, followed by the length of the layer.
+    offsets: Vec<Vec<u64>>,
+}
+
+impl SymmetricIndex {
+    /// The index of the boards with `walls`, or `None` if they leave more than
+    /// [`MAX_LAYERED_SQUARES`] squares open.
+    pub fn new(walls: u64) -> Option<Self> {
+        let walls = walls & BB_ALL;
+        let symmetries = Symmetries::new(walls);
+        let open = symmetries.squares().len();
+        if open > MAX_LAYERED_SQUARES {
+            return None;
+        }
+        let white_sets: Vec<Vec<u64>> = (0..=open).map(|count| symmetries.smallest_sets(count)).collect();
+        let offsets = (0..=open)
+            .map(|pieces| {
+                let mut offsets = vec![0];
+                for white in 0..=pieces {
+                    let len = white_sets[white].len() as u64 * BINOMIAL[open - white][pieces - white] * 2;
+                    offsets.push(offsets[white] + len);
+                }
+                offsets
+            })
+            .collect();
+        Some(Self { walls, symmetries, white_sets, offsets })
+    }
+
+    pub fn walls(&self) -> u64 {
+        self.walls
+    }
+
+    /// The number of layers, one for each number of pieces from none to a full board.
+    pub fn layers(&self) -> u32 {
+        self.symmetries.squares().len() as u32 + 1
+    }
+
+    /// The number of indices in the layer of positions with `pieces` pieces.
+    pub fn layer_len(&self, pieces: u32) -> u64 {
+        *self.offsets[pieces as usize].last().expect("every layer has an end")
+    }
+
+    /// The layer and index of `board`, or `None` if its walls are not the ones of this index.
+    /// Move counters are ignored.
+    pub fn index_of(&self, board: &Board) -> Option<(u32, u64)> {
+        (board.walls & BB_ALL == self.walls).then(|| self.position_index(board))
+    }
+
+    /// The position with `index` in the layer of `pieces` pieces, with its move counters at
+    /// zero.
+    pub fn board_at(&self, pieces: u32, index: u64) -> Board {
+        let offsets = &self.offsets[pieces as usize];
+        let white_count = offsets.partition_point(|&offset| offset <= index) - 1;
+        let black_count = pieces as usize - white_count;
+        let free = self.symmetries.squares().len() - white_count;
+        let black_sets = BINOMIAL[free][black_count];
+        let pair = (index - offsets[white_count]) / 2;
+
+        let white = self.white_sets[white_count][(pair / black_sets) as usize];
+        let black = subset_unrank(pair % black_sets, black_count, white, free);
+        Board {
+            white: self.symmetries.board_set(white),
+            black: self.symmetries.board_set(black),
+            walls: self.walls | RANK_8 | FILE_H,
+            ply: (index & 1) as u16,
+            halfmove: 0,
+        }
+    }
+
+    fn position_index(&self, board: &Board) -> (u32, u64) {
+        let sym = &self.symmetries;
+        let (white, black) = (sym.open_set(board.white), sym.open_set(board.black));
+        let pieces = (white | black).count_ones() as usize;
+        let (white, black_rank) = sym.smallest_image(white, black);
+        let white_count = white.count_ones() as usize;
+        let black_sets = BINOMIAL[sym.squares().len() - white_count][pieces - white_count];
+        let white_rank = self.white_sets[white_count].binary_search(&white).expect("smallest images are listed") as u64;
+        let pair = white_rank * black_sets + black_rank;
+        (pieces as u32, self.offsets[pieces][white_count] + pair * 2 + u64::from(board.turn() == Player::Black))
+    }
+}
+
+/// The values of one layer of a [`SymmetricIndex`], four to a byte, lowest bits first.
+struct Layer {
+    values: Vec<AtomicU8>,
+}
+
+impl Layer {
+    fn new(len: u64) -> Self {
+        Self { values: (0..packed_len(len)).map(|_| AtomicU8::new(UNKNOWN)).collect() }
+    }
+
+    fn get(&self, i: u64) -> u8 {
+        self.values[(i / 4) as usize].load(Ordering::Relaxed) >> (i % 4 * 2) & 3
+    }
+
+    /// Sets the value of an unknown position.
+    fn set(&self, i: u64, value: u8) {
+        self.values[(i / 4) as usize].fetch_or(value << (i % 4 * 2), Ordering::Relaxed);
+    }
+
+    fn write(&self, out: &mut impl Write, walls: u64, pieces: u32) -> io::Result<()> {
+        out.write_all(LAYER_MAGIC)?;
+        out.write_all(&walls.to_le_bytes())?;
+        out.write_all(&u64::from(pieces).to_le_bytes())?;
+        for chunk in self.values.chunks(1 << 16) {
+            let bytes: Vec<u8> = chunk.iter().map(|value| value.load(Ordering::Relaxed)).collect();
+            out.write_all(&bytes)?;
+        }
+        out.flush()
+    }
+
+    fn read(mut input: impl Read, index: &SymmetricIndex, pieces: u32) -> io::Result<Self> {
+        read_layer_header(&mut input, Some(index.walls), pieces)?;
+        let mut values = Vec::with_capacity(packed_len(index.layer_len(pieces)));
+        let mut buf = vec![0; 1 << 16];
+        loop {
+            let read = input.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            values.extend(buf[..read].iter().map(|&byte| AtomicU8::new(byte)));
+        }
+        if values.len() != packed_len(index.layer_len(pieces)) {
+            return Err(invalid_data("wrong layer size"));
+        }
+        Ok(Self { values })
+    }
+}
+
+/// Checks the header of the layer file of `pieces` pieces, returning its walls. They must be
+/// `walls` if that is given.
+fn read_layer_header(input: &mut impl Read, walls: Option<u64>, pieces: u32) -> io::Result<u64> {
+    let mut header = [0; LAYER_HEADER_LEN as usize];
+    input.read_exact(&mut header)?;
+    if header[..8] != LAYER_MAGIC[..] {
+        return Err(invalid_data("not a WDL layer"));
+    }
+    let file_walls = u64::from_le_bytes(header[8..16].try_into().expect("eight bytes"));
+    if walls.is_some_and(|walls| walls != file_walls) {
+        return Err(invalid_data("layer of a table with other walls"));
+    }
+    if u64::from_le_bytes(header[16..].try_into().expect("eight bytes")) != u64::from(pieces) {
+        return Err(invalid_data("layer with the wrong number of pieces"));
+    }
+    Ok(file_walls)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn layer_path(dir: &Path, pieces: u32) -> PathBuf {
+    dir.join(format!("layer{pieces:02}.wdl"))
+}
+
+/// Solves every position of `index` on `threads` threads, writing each layer to its own file
+/// in `dir` once it is solved.
+///
+/// Layers are solved from the full board down as in [`Table::generate`], but only the layer
+/// being solved and the one after it are held in memory, at two bits a position. On a 5x5
+/// board the largest layers have about 3.5e10 indices, so the two take around 18 GB. Layers
+/// already in `dir` are kept, so an interrupted run picks up where it stopped. `progress` is
+/// called with the number of pieces of each layer as it is written.
+pub fn generate_layers(
+    index: &SymmetricIndex,
+    dir: &Path,
+    threads: usize,
+    mut progress: impl FnMut(u32),
+) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut next: Option<Layer> = None;
+    for pieces in (0..index.layers()).rev() {
+        let path = layer_path(dir, pieces);
+        if path.exists() {
+            // a finished layer is only needed to solve the one below it.
+            next = match pieces.checked_sub(1) {
+                Some(below) if !layer_path(dir, below).exists() => Some(Layer::read(File::open(&path)?, index, pieces)?),
+                _ => None,
+            };
+            continue;
+        }
+        let layer = solve_layer(index, pieces, next.as_ref(), threads);
+        // written under another name first, so that a layer file is always complete.
+        let partial = path.with_extension("partial");
+        layer.write(&mut BufWriter::new(File::create(&partial)?), index.walls, pieces)?;
+        fs::rename(&partial, &path)?;
+        progress(pieces);
+        next = Some(layer);
+    }
+    Ok(())
+}
+
+/// Solves the layer of `pieces` pieces given the solved layer of one more piece, revisiting
+/// its positions until none changes as in [`Table::generate`].
+fn solve_layer(index: &SymmetricIndex, pieces: u32, next: Option<&Layer>, threads: usize) -> Layer {
+    let len = index.layer_len(pieces);
+    let layer = Layer::new(len);
+    let chunk_size = len.div_ceil(threads.max(1) as u64).max(1);
+    loop {
+        let changed = AtomicBool::new(false);
+        std::thread::scope(|s| {
+            for start in (0..len).step_by(chunk_size as usize) {
+                let (layer, changed) = (&layer, &changed);
+                s.spawn(move || {
+                    for i in (start..(start + chunk_size).min(len)).filter(|&i| layer.get(i) == UNKNOWN) {
+                        let value = resolve(&index.board_at(pieces, i), |child| match index.position_index(child) {
+                            (p, j) if p == pieces => layer.get(j),
+                            (_, j) => next.expect("singles lead to the next layer").get(j),
+                        });
+                        if value != UNKNOWN {
+                            layer.set(i, value);
+                            changed.store(true, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        if !changed.into_inner() {
+            break;
+        }
+    }
+    for i in (0..len).filter(|&i| layer.get(i) == UNKNOWN) {
+        layer.set(i, DRAW);
+    }
+    layer
+}
+
+/// A win/draw/loss table written by [`generate_layers`], probed on disk a position at a time.
+pub struct LayeredTable {
+    index: SymmetricIndex,
+    /// The file of each layer, by number of pieces.
+    files: Vec<File>,
+}
+
+impl LayeredTable {
+    /// Opens the table in `dir`, which must have every layer.
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        let walls = read_layer_header(&mut File::open(layer_path(dir, 0))?, None, 0)?;
+        let index = SymmetricIndex::new(walls).ok_or_else(|| invalid_data("too many open squares"))?;
+        let files = (0..index.layers())
+            .map(|pieces| {
+                let mut file = File::open(layer_path(dir, pieces))?;
+                read_layer_header(&mut file, Some(walls), pieces)?;
+                if file.metadata()?.len() != LAYER_HEADER_LEN + packed_len(index.layer_len(pieces)) as u64 {
+                    return Err(invalid_data("wrong layer size"));
+                }
+                Ok(file)
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self { index, files })
+    }
+
+    pub fn index(&self) -> &SymmetricIndex {
+        &self.index
+    }
+
+    /// The value of `board`, or `None` if the table is for other walls.
+    pub fn probe(&mut self, board: &Board) -> io::Result<Option<Wdl>> {
+        let Some((pieces, i)) = self.index.index_of(board) else {
+            return Ok(None);
+        };
+        let file = &mut self.files[pieces as usize];
+        file.seek(SeekFrom::Start(LAYER_HEADER_LEN + i / 4))?;
+        let mut byte = [0];
+        file.read_exact(&mut byte)?;
+        Ok(Some(Wdl::from_code(byte[0] >> (i % 4 * 2) & 3)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_layers, layer_path, Index, LayeredTable, SymmetricIndex, Table, Wdl, MAX_LAYERED_SQUARES};
+    use crate::{
+        dfpn::{Dfpn, Proof},
+        Board, BB_ALL,
+    };
+
+    #[test]
+    fn indexes_small_boards() {
+        assert_eq!(Board::small(7), Board::new());
+        let board = Board::small(3);
+        assert_eq!(board.fen(), "-------/-------/-------/-------/x1o----/3----/o1x---- x 0 1");
+
+        let index = Index::new(board.walls()).unwrap();
+        assert_eq!(index.len(), 2 * 3u64.pow(9));
+        let mut all: Vec<u64> = (0..=9).flat_map(|occupied| index.layer(occupied)).collect();
+        all.sort_unstable();
+        assert!(all.iter().copied().eq(0..index.len()));
+        for i in all {
+            assert_eq!(index.index_of(&index.board_at(i)), Some(i));
+        }
+        assert_eq!(index.index_of(&Board::small(4)), None);
+        assert!(Index::new(Board::small(5).walls()).is_none());
+    }
+
+    #[test]
+    fn solves_a_3x3_board() {
+        let index = Index::new(Board::small(3).walls()).unwrap();
+        let table = Table::generate(index.clone(), 3);
+        for i in 0..index.len() {
+            let board = index.board_at(i);
+            if board.game_over() {
+                continue;
+            }
+            let mut children = Vec::new();
+            board.generate_moves(|mv| {
+                let mut child = board;
+                child.make_move(mv);
+                children.push(table.probe(&child).unwrap());
+                false
+            });
+            let expected = if children.contains(&Wdl::Loss) {
+                Wdl::Win
+            } else if children.iter().all(|&value| value == Wdl::Win) {
+                Wdl::Loss
+            } else {
+                Wdl::Draw
+            };
+            assert_eq!(table.get(i), expected, "{}", board.fen());
+        }
+
+        // wins on so small a board come long before the 50-move rule.
+        let mut dfpn = Dfpn::new();
+        for i in (0..index.len()).step_by(97) {
+            let board = index.board_at(i);
+            match table.get(i) {
+                Wdl::Win => assert_eq!(dfpn.solve(&board).proof, Proof::Proven, "{}", board.fen()),
+                Wdl::Loss => assert_eq!(dfpn.solve(&board).proof, Proof::Disproven, "{}", board.fen()),
+                Wdl::Draw => {}
+            }
+        }
+
+        let mut file = Vec::new();
+        table.write(&mut file).unwrap();
+        let read = Table::read(file.as_slice()).unwrap();
+        assert_eq!(read.probe(&Board::small(3)), table.probe(&Board::small(3)));
+        assert!(read.values == table.values && read.index == table.index);
+        assert!(Table::read(&file[..file.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn indexes_up_to_symmetry() {
+        // corner boards have all eight symmetries, and walls on a diagonal keep only the four
+        // that send the diagonal to itself.
+        let diagonal = "-------/-------/-------/4---/2-1---/1-2---/4--- x 0 1".parse::<Board>().unwrap().walls();
+        for (walls, images) in [(Board::small(3).walls(), 8), (Board::small(4).walls(), 8), (diagonal, 4)] {
+            let full = Index::new(walls).unwrap();
+            let index = SymmetricIndex::new(walls).unwrap();
+            assert_eq!(index.symmetries.len(), images);
+            let len: u64 = (0..index.layers()).map(|pieces| index.layer_len(pieces)).sum();
+            assert!(len * images as u64 >= full.len() && len < full.len() / 2, "{len} of {}", full.len());
+            for i in (0..full.len()).step_by(if full.len() < 100_000 { 1 } else { 9973 }) {
+                let board = full.board_at(i);
+                let (pieces, j) = index.index_of(&board).unwrap();
+                assert!(j < index.layer_len(pieces));
+                let image = index.board_at(pieces, j);
+                assert_eq!(index.index_of(&image), Some((pieces, j)), "{}", board.fen());
+                assert_eq!(image.white.count_ones(), board.white.count_ones());
+                assert_eq!(image.turn(), board.turn());
+            }
+        }
+        assert_eq!((BB_ALL & !Board::small(5).walls()).count_ones() as usize, MAX_LAYERED_SQUARES);
+        assert!(SymmetricIndex::new(Board::small(6).walls()).is_none());
+    }
+
+    #[test]
+    fn solves_layers_like_a_table() {
+        let walls = Board::small(3).walls();
+        let full = Index::new(walls).unwrap();
+        let table = Table::generate(full.clone(), 2);
+        let dir = std::env::temp_dir().join(format!("ataxxgen-retro-{}", std::process::id()));
+        let index = SymmetricIndex::new(walls).unwrap();
+        generate_layers(&index, &dir, 3, |_| {}).unwrap();
+
+        // a layer lost part way through is solved again from the ones kept.
+        std::fs::remove_file(layer_path(&dir, 4)).unwrap();
+        std::fs::remove_file(layer_path(&dir, 2)).unwrap();
+        let mut solved = Vec::new();
+        generate_layers(&index, &dir, 1, |pieces| solved.push(pieces)).unwrap();
+        assert_eq!(solved, [4, 2]);
+
+        let mut layered = LayeredTable::open(&dir).unwrap();
+        for i in 0..full.len() {
+            let board = full.board_at(i);
+            assert_eq!(layered.probe(&board).unwrap(), Some(table.get(i)), "{}", board.fen());
+        }
+        assert_eq!(layered.probe(&Board::new()).unwrap(), None);
+
+        std::fs::write(layer_path(&dir, 5), b"ATXWDL02").unwrap();
+        assert!(LayeredTable::open(&dir).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,380 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    endgame::{self, Solver},
+    eval::Evaluator,
+    expand,
+    tt::{Bound, TranspositionTable},
+    Board, Move, Player,
+};
+
+/// The score of a won terminal position at the root. Wins further from the root score less.
+pub const SCORE_WIN: i32 = 30_000;
+/// Scores at least this large in magnitude are forced wins or losses.
+pub const SCORE_WIN_BOUND: i32 = SCORE_WIN - 1_000;
+/// Scores at least this large in magnitude, but below `SCORE_WIN_BOUND`, are endgames solved
+/// on disc difference, whose distance to the end is not known.
+pub const SCORE_SOLVED_BOUND: i32 = SCORE_WIN_BOUND - 100;
+const INFINITY: i32 = SCORE_WIN + 1;
+pub const MAX_DEPTH: u8 = 64;
+
+/// Conditions under which a search stops. Any limit that is hit ends the search, though the
+/// first iteration is always completed so that a move is available.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+}
+
+impl Limits {
+    pub fn depth(depth: u8) -> Self {
+        Self { depth: Some(depth), ..Self::default() }
+    }
+
+    pub fn nodes(nodes: u64) -> Self {
+        Self { nodes: Some(nodes), ..Self::default() }
+    }
+
+    pub fn time(time: Duration) -> Self {
+        Self { time: Some(time), ..Self::default() }
+    }
+}
+
+/// Tunable constants of the search's pruning and reductions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Params {
+    /// The deepest remaining depth at which reverse futility pruning is tried.
+    pub rfp_depth: u8,
+    /// How far, per ply of remaining depth, the static evaluation must exceed beta to prune.
+    pub rfp_margin: i32,
+    /// The shallowest remaining depth at which late moves are reduced.
+    pub lmr_min_depth: u8,
+    /// The number of moves searched at full depth before reducing the rest.
+    pub lmr_min_moves: usize,
+    /// How many plies late moves are reduced by.
+    pub lmr_reduction: u8,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self { rfp_depth: 2, rfp_margin: 150, lmr_min_depth: 3, lmr_min_moves: 4, lmr_reduction: 1 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    /// The best move found, or `None` if the position is terminal.
+    pub best_move: Option<Move>,
+    /// The score of the position from the side to move's perspective.
+    pub score: i32,
+    /// The deepest completed iteration.
+    pub depth: u8,
+    pub nodes: u64,
+}
+
+/// An iterative-deepening alpha-beta search.
+pub struct Search {
+    tt: TranspositionTable,
+    pub evaluator: Evaluator,
+    pub params: Params,
+    /// Solves the root exactly instead of searching it when it has few enough empty squares.
+    /// The solver gets half of the search's node and time limits, and the search falls back
+    /// to the usual alpha-beta if it gives up.
+    pub endgame: Option<Solver>,
+    nodes: u64,
+    limits: Limits,
+    start: Instant,
+    stopped: bool,
+    root_best: Option<Move>,
+}
+
+impl Search {
+    pub fn new(tt_mb: usize) -> Self {
+        Self {
+            tt: TranspositionTable::new(tt_mb),
+            evaluator: Evaluator::default(),
+            params: Params::default(),
+            endgame: None,
+            nodes: 0,
+            limits: Limits::default(),
+            start: Instant::now(),
+            stopped: false,
+            root_best: None,
+        }
+    }
+
+    pub fn tt(&self) -> &TranspositionTable {
+        &self.tt
+    }
+
+    /// Forgets everything learned in previous searches.
+    pub fn clear(&mut self) {
+        self.tt.clear();
+        if let Some(solver) = &mut self.endgame {
+            solver.clear();
+        }
+    }
+
+    pub fn search(&mut self, board: &Board, limits: Limits) -> SearchResult {
+        self.search_with_info(board, limits, |_| {})
+    }
+
+    /// Searches `board`, calling `info` with the result of every completed iteration.
+    pub fn search_with_info(
+        &mut self,
+        board: &Board,
+        limits: Limits,
+        mut info: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        self.tt.new_search();
+        self.nodes = 0;
+        self.limits = limits;
+        self.start = Instant::now();
+        self.stopped = false;
+        self.root_best = None;
+
+        let mut result = SearchResult { best_move: None, score: 0, depth: 0, nodes: 0 };
+        if let Some(score) = terminal_score(board, 0) {
+            result.score = score;
+            return result;
+        }
+        if let Some(solver) = self.endgame.as_mut().filter(|solver| solver.applies(board)) {
+            let max_nodes = limits.nodes.map(|n| n / 2);
+            let deadline = limits.time.map(|t| self.start + t / 2);
+            if let Some(solution) = solver.solve_within(board, max_nodes, deadline) {
+                result = SearchResult {
+                    best_move: solution.best_move,
+                    score: endgame::search_score(solution.score),
+                    depth: MAX_DEPTH,
+                    nodes: solution.nodes,
+                };
+                info(&result);
+                return result;
+            }
+            self.nodes = solver.nodes();
+        }
+
+        let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        for depth in 1..=max_depth {
+            let score = self.negamax(board, depth, 0, -INFINITY, INFINITY);
+            if self.stopped && depth > 1 {
+                break;
+            }
+            result = SearchResult {
+                best_move: self.root_best,
+                score,
+                depth,
+                nodes: self.nodes,
+            };
+            info(&result);
+            if self.stopped || score.abs() >= SCORE_WIN_BOUND {
+                break;
+            }
+        }
+
+        result.nodes = self.nodes;
+        result
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.limits.nodes.is_some_and(|n| self.nodes >= n)
+            || (self.nodes.is_multiple_of(1024) && self.limits.time.is_some_and(|t| self.start.elapsed() >= t))
+        {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+
+    fn negamax(&mut self, board: &Board, depth: u8, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+
+        if let Some(score) = terminal_score(board, ply) {
+            return score;
+        }
+        if depth == 0 {
+            return self.evaluator.evaluate(board);
+        }
+        if ply > 0 && self.should_stop() {
+            return 0;
+        }
+
+        let key = board.key();
+        let tt_entry = self.tt.probe(key);
+        let mut tt_move = None;
+        if let Some(entry) = tt_entry {
+            tt_move = entry.mv;
+            let score = score_from_tt(i32::from(entry.score), ply);
+            if ply > 0
+                && entry.depth >= depth
+                && match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                    Bound::None => false,
+                }
+            {
+                return score;
+            }
+        }
+
+        if ply > 0 && depth <= self.params.rfp_depth && beta.abs() < SCORE_WIN_BOUND {
+            let eval = self.evaluator.evaluate(board);
+            if eval - self.params.rfp_margin * i32::from(depth) >= beta {
+                return eval;
+            }
+        }
+
+        let moves = ordered_moves(board, tt_move);
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for (i, &(mv, order)) in moves.iter().enumerate() {
+            let mut child = *board;
+            child.make_move(mv);
+            // moves late in the ordering that gain little material are searched shallower first,
+            // and only searched fully if they turn out to be good.
+            let reduction = if depth >= self.params.lmr_min_depth && i >= self.params.lmr_min_moves && order < 10 {
+                self.params.lmr_reduction.min(depth - 1)
+            } else {
+                0
+            };
+            let mut score = -self.negamax(&child, depth - 1 - reduction, ply + 1, -beta, -alpha);
+            if reduction > 0 && score > alpha && !self.stopped {
+                score = -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha);
+            }
+            if self.stopped && ply > 0 {
+                return 0;
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+                if ply == 0 {
+                    self.root_best = best_move;
+                }
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+            if self.stopped {
+                // at the root, keep what the completed moves have found.
+                break;
+            }
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        if !self.stopped {
+            self.tt.store(key, best_move, score_to_tt(best_score, ply) as i16, depth, bound);
+        }
+
+        best_score
+    }
+}
+
+/// Calls `f` on every item, splitting `items` into contiguous chunks between the given
+/// searchers, one thread each.
+pub fn for_each_split<T: Send>(items: &mut [T], searches: &mut [Search], f: impl Fn(&mut Search, &mut T) + Sync) {
+    if items.is_empty() || searches.is_empty() {
+        return;
+    }
+    let chunk_size = items.len().div_ceil(searches.len());
+    let f = &f;
+    std::thread::scope(|s| {
+        for (chunk, search) in items.chunks_mut(chunk_size).zip(searches.iter_mut()) {
+            s.spawn(move || {
+                for item in chunk {
+                    f(search, item);
+                }
+            });
+        }
+    });
+}
+
+/// The score of a finished game from the side to move's perspective, or `None` if the game continues.
+pub fn terminal_score(board: &Board, ply: i32) -> Option<i32> {
+    board.outcome().map(|winner| match winner {
+        None => 0,
+        Some(p) if p == board.turn() => SCORE_WIN - ply,
+        Some(_) => -SCORE_WIN + ply,
+    })
+}
+
+/// Converts a score relative to the root into one relative to the current node, for storage.
+fn score_to_tt(score: i32, ply: i32) -> i32 {
+    if score >= SCORE_WIN_BOUND {
+        score + ply
+    } else if score <= -SCORE_WIN_BOUND {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: i32) -> i32 {
+    if score >= SCORE_WIN_BOUND {
+        score - ply
+    } else if score <= -SCORE_WIN_BOUND {
+        score + ply
+    } else {
+        score
+    }
+}
+
+/// Generates the moves of `board`, best first: the hash move, then by the material they gain.
+fn ordered_moves(board: &Board, tt_move: Option<Move>) -> Vec<(Move, i32)> {
+    let them = match board.turn() {
+        Player::White => board.black,
+        Player::Black => board.white,
+    };
+    let mut moves = Vec::with_capacity(64);
+    board.generate_moves(|mv| {
+        let score = if Some(mv) == tt_move {
+            i32::MAX
+        } else {
+            match mv {
+                Move::Single { to } => 10 * (expand(to.as_set()) & them).count_ones() as i32 + 5,
+                Move::Double { to, .. } => 10 * (expand(to.as_set()) & them).count_ones() as i32 - 5,
+                Move::Pass => 0,
+            }
+        };
+        moves.push((mv, score));
+        false
+    });
+    moves.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Limits, Search, SCORE_WIN_BOUND};
+    use crate::{Board, Player};
+
+    #[test]
+    fn finds_immediate_win() {
+        let board: Board = "7/7/7/7/7/1o5/x6 x 0 1".parse().unwrap();
+        let result = Search::new(1).search(&board, Limits::depth(3));
+        let mut after = board;
+        after.make_move(result.best_move.unwrap());
+        assert_eq!(after.outcome(), Some(Some(Player::White)));
+        assert!(result.score >= SCORE_WIN_BOUND);
+    }
+
+    #[test]
+    fn node_limited_search_is_deterministic() {
+        let board = Board::default();
+        let a = Search::new(1).search(&board, Limits::nodes(5_000));
+        let b = Search::new(1).search(&board, Limits::nodes(5_000));
+        assert_eq!(a, b);
+        assert!(a.best_move.is_some());
+        assert!(a.nodes < 5_000 + 200);
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, Write},
+    str::FromStr,
+    sync::atomic::{AtomicI64, AtomicUsize, Ordering},
+};
+
+use crate::{
+    mcts::{self, Mcts},
+    mix,
+    openings::random_opening,
+    rng::Rng,
+    search::{self, Search},
+    Board, Player,
+};
+
+/// The names of the engine parameters that can be tuned, as accepted by [`EngineConfig::set`].
+pub const PARAM_NAMES: [&str; 6] =
+    ["rfp_depth", "rfp_margin", "lmr_min_depth", "lmr_min_moves", "lmr_reduction", "mcts_exploration"];
+
+/// The settings of the in-process engines that tuning games are played with.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EngineConfig {
+    pub search: search::Params,
+    pub mcts: mcts::Params,
+}
+
+impl EngineConfig {
+    /// Sets a parameter by name. Integer parameters are rounded to the nearest valid value.
+    pub fn set(&mut self, name: &str, value: f64) -> Result<(), String> {
+        let int = value.round().max(0.0);
+        match name {
+            "rfp_depth" => self.search.rfp_depth = int.min(f64::from(u8::MAX)) as u8,
+            "rfp_margin" => self.search.rfp_margin = int as i32,
+            "lmr_min_depth" => self.search.lmr_min_depth = int.min(f64::from(u8::MAX)) as u8,
+            "lmr_min_moves" => self.search.lmr_min_moves = int as usize,
+            "lmr_reduction" => self.search.lmr_reduction = int.min(f64::from(u8::MAX)) as u8,
+            "mcts_exploration" => self.mcts.exploration = value,
+            _ => return Err(format!("unknown parameter: {name}")),
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<f64> {
+        Some(match name {
+            "rfp_depth" => f64::from(self.search.rfp_depth),
+            "rfp_margin" => f64::from(self.search.rfp_margin),
+            "lmr_min_depth" => f64::from(self.search.lmr_min_depth),
+            "lmr_min_moves" => self.search.lmr_min_moves as f64,
+            "lmr_reduction" => f64::from(self.search.lmr_reduction),
+            "mcts_exploration" => self.mcts.exploration,
+            _ => return None,
+        })
+    }
+}
+
+/// A parameter being tuned, with its schedule in the usual fishtest form.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+    /// The size of the perturbation at the final iteration.
+    pub c_end: f64,
+    /// The learning rate at the final iteration, as a multiple of `c_end` squared.
+    pub r_end: f64,
+}
+
+impl Param {
+    pub fn new(name: &str, value: f64, min: f64, max: f64, c_end: f64, r_end: f64) -> Self {
+        Self { name: name.to_owned(), value, min, max, c_end, r_end }
+    }
+}
+
+/// Parses `name,value,min,max,c_end,r_end`.
+impl FromStr for Param {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split(',').map(str::trim).collect();
+        let [name, rest @ ..] = fields.as_slice() else {
+            return Err(format!("invalid parameter: {s}"));
+        };
+        let numbers = rest
+            .iter()
+            .map(|f| f.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("invalid parameter: {s}"))?;
+        let &[value, min, max, c_end, r_end] = numbers.as_slice() else {
+            return Err(format!("expected name,value,min,max,c_end,r_end: {s}"));
+        };
+        if min > max || !(min..=max).contains(&value) {
+            return Err(format!("value outside of its range: {s}"));
+        }
+        Ok(Self::new(name, value, min, max, c_end, r_end))
+    }
+}
+
+impl Display for Param {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{},{},{},{}", self.name, self.value, self.min, self.max, self.c_end, self.r_end)
+    }
+}
+
+/// Every tunable parameter at its default value, with ranges and step sizes suited to it.
+pub fn default_params() -> Vec<Param> {
+    let config = EngineConfig::default();
+    let value = |name| config.get(name).unwrap();
+    vec![
+        Param::new("rfp_depth", value("rfp_depth"), 0.0, 6.0, 0.5, 0.002),
+        Param::new("rfp_margin", value("rfp_margin"), 20.0, 400.0, 15.0, 0.002),
+        Param::new("lmr_min_depth", value("lmr_min_depth"), 1.0, 8.0, 0.5, 0.002),
+        Param::new("lmr_min_moves", value("lmr_min_moves"), 1.0, 20.0, 1.0, 0.002),
+        Param::new("lmr_reduction", value("lmr_reduction"), 0.0, 4.0, 0.5, 0.002),
+        Param::new("mcts_exploration", value("mcts_exploration"), 0.2, 4.0, 0.1, 0.002),
+    ]
+}
+
+/// Which in-process engine plays the tuning games, and how long it thinks per move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Engine {
+    AlphaBeta { nodes: u64 },
+    Mcts { visits: u64 },
+}
+
+/// Plays one game from `opening`, returning the winner or `None` for a draw.
+pub fn play_game(
+    opening: &Board,
+    white: &EngineConfig,
+    black: &EngineConfig,
+    engine: Engine,
+    rng: &mut Rng,
+) -> Option<Player> {
+    let mut searches = [white, black].map(|config| {
+        let mut search = Search::new(1);
+        search.params = config.search;
+        search
+    });
+    let mut board = *opening;
+    while !board.game_over() {
+        let side = board.turn() as usize;
+        let config = if board.turn() == Player::White { white } else { black };
+        let mv = match engine {
+            Engine::AlphaBeta { nodes } => searches[side].search(&board, search::Limits::nodes(nodes)).best_move,
+            Engine::Mcts { visits } => {
+                Mcts::new(board, config.mcts, Rng::new(rng.next_u64())).search(mcts::Limits::visits(visits))
+            }
+        };
+        let Some(mv) = mv else { break };
+        board.make_move(mv);
+    }
+    board.outcome().flatten()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options {
+    pub engine: Engine,
+    /// The number of iterations the schedule is planned for.
+    pub iterations: u64,
+    /// Game pairs played per iteration, each pair being one opening played with both colours.
+    pub pairs: usize,
+    pub threads: usize,
+    /// Random moves from the start position that make up an opening when no book is given.
+    /// If no game is still in play after this many moves, games start from the start position.
+    pub random_plies: u32,
+    pub seed: u64,
+    /// Decay exponent of the learning rate.
+    pub alpha: f64,
+    /// Decay exponent of the perturbation size.
+    pub gamma: f64,
+    /// Stability constant of the learning rate, as a fraction of `iterations`.
+    pub a_ratio: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            engine: Engine::AlphaBeta { nodes: 2_000 },
+            iterations: 1_000,
+            pairs: 1,
+            threads: 1,
+            random_plies: 4,
+            seed: 0,
+            alpha: 0.602,
+            gamma: 0.101,
+            a_ratio: 0.1,
+        }
+    }
+}
+
+/// Simultaneous perturbation stochastic approximation over engine parameters, by self-play.
+///
+/// Every iteration perturbs all parameters at once in random directions, plays paired games
+/// between the plus and minus variants, and moves each parameter in the direction of the
+/// perturbation by an amount proportional to the plus side's net wins.
+pub struct Spsa {
+    params: Vec<Param>,
+    base: EngineConfig,
+    openings: Vec<Board>,
+    options: Options,
+    rng: Rng,
+    iteration: u64,
+}
+
+impl Spsa {
+    /// Creates a tuner for `params` on top of `base`. Games start from `openings`, or from
+    /// random positions if it is empty.
+    pub fn new(params: Vec<Param>, base: EngineConfig, openings: Vec<Board>, options: Options) -> Result<Self, String> {
+        let mut check = base;
+        for param in &params {
+            check.set(&param.name, param.value)?;
+        }
+        Ok(Self { params, base, openings, options, rng: Rng::new(options.seed), iteration: 0 })
+    }
+
+    pub fn params(&self) -> &[Param] {
+        &self.params
+    }
+
+    /// The number of iterations completed so far.
+    pub fn iteration(&self) -> u64 {
+        self.iteration
+    }
+
+    /// The engine configuration with the current parameter values.
+    pub fn config(&self) -> EngineConfig {
+        self.config_with(|_, param| param.value)
+    }
+
+    fn config_with(&self, value: impl Fn(usize, &Param) -> f64) -> EngineConfig {
+        let mut config = self.base;
+        for (i, param) in self.params.iter().enumerate() {
+            config.set(&param.name, value(i, param).clamp(param.min, param.max)).unwrap();
+        }
+        config
+    }
+
+    fn opening(&self, rng: &mut Rng) -> Board {
+        if !self.openings.is_empty() {
+            return self.openings[rng.range(0, self.openings.len())];
+        }
+        random_opening(self.options.random_plies, rng).unwrap_or_default()
+    }
+
+    /// Plays the game pairs between `plus` and `minus`, returning plus's score minus minus's
+    /// score, in half points.
+    fn play_pairs(&self, plus: &EngineConfig, minus: &EngineConfig) -> i64 {
+        let next = AtomicUsize::new(0);
+        let total = AtomicI64::new(0);
+        let base_seed = mix(self.options.seed ^ mix(self.iteration));
+        std::thread::scope(|s| {
+            for _ in 0..self.options.threads.clamp(1, self.options.pairs.max(1)) {
+                s.spawn(|| loop {
+                    let pair = next.fetch_add(1, Ordering::Relaxed);
+                    if pair >= self.options.pairs {
+                        break;
+                    }
+                    let mut rng = Rng::new(base_seed.wrapping_add(pair as u64));
+                    let opening = self.opening(&mut rng);
+                    let mut score = 0;
+                    for (white, black, plus_side) in [(plus, minus, Player::White), (minus, plus, Player::Black)] {
+                        score += match play_game(&opening, white, black, self.options.engine, &mut rng) {
+                            Some(winner) if winner == plus_side => 2,
+                            Some(_) => -2,
+                            None => 0,
+                        };
+                    }
+                    total.fetch_add(score, Ordering::Relaxed);
+                });
+            }
+        });
+        total.into_inner()
+    }
+
+    /// Runs one iteration, returning the plus variant's net wins in its games.
+    pub fn step(&mut self) -> f64 {
+        let k = self.iteration as f64;
+        let n = self.options.iterations.max(1) as f64;
+        let big_a = self.options.a_ratio * n;
+
+        let deltas: Vec<f64> =
+            self.params.iter().map(|_| if self.rng.next_u64() & 1 == 0 { 1.0 } else { -1.0 }).collect();
+        let steps: Vec<f64> = self
+            .params
+            .iter()
+            .map(|param| param.c_end * n.powf(self.options.gamma) / (k + 1.0).powf(self.options.gamma))
+            .collect();
+
+        let plus = self.config_with(|i, param| param.value + steps[i] * deltas[i]);
+        let minus = self.config_with(|i, param| param.value - steps[i] * deltas[i]);
+
+        let result = self.play_pairs(&plus, &minus) as f64 / 2.0;
+        for ((param, delta), c) in self.params.iter_mut().zip(&deltas).zip(&steps) {
+            let a_end = param.r_end * param.c_end * param.c_end;
+            let a = a_end * (big_a + n).powf(self.options.alpha) / (big_a + k + 1.0).powf(self.options.alpha);
+            param.value = (param.value + a / c * result * delta).clamp(param.min, param.max);
+        }
+        self.iteration += 1;
+        result
+    }
+
+    /// Runs the remaining iterations, logging the result and the parameter values after each
+    /// one to `csv`, preceded by a header row if no iterations have run yet.
+    pub fn run(&mut self, csv: &mut impl Write, mut progress: impl FnMut(&Self, f64)) -> io::Result<()> {
+        if self.iteration == 0 {
+            let names: Vec<&str> = self.params.iter().map(|p| p.name.as_str()).collect();
+            writeln!(csv, "iteration,result,{}", names.join(","))?;
+        }
+        while self.iteration < self.options.iterations {
+            let result = self.step();
+            let values: Vec<String> = self.params.iter().map(|p| p.value.to_string()).collect();
+            writeln!(csv, "{},{result},{}", self.iteration, values.join(","))?;
+            csv.flush()?;
+            progress(self, result);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_params, EngineConfig, Engine, Options, Param, Spsa, PARAM_NAMES};
+
+    #[test]
+    fn params_round_trip() {
+        let mut config = EngineConfig::default();
+        for name in PARAM_NAMES {
+            let value = config.get(name).unwrap();
+            config.set(name, value).unwrap();
+        }
+        assert_eq!(config, EngineConfig::default());
+        config.set("rfp_margin", 123.4).unwrap();
+        assert_eq!(config.search.rfp_margin, 123);
+        assert!(config.set("nonsense", 1.0).is_err());
+
+        let param: Param = "rfp_margin,150,20,400,15,0.002".parse().unwrap();
+        assert_eq!(param.to_string().parse::<Param>().unwrap(), param);
+        assert!("rfp_margin,500,20,400,15,0.002".parse::<Param>().is_err());
+        assert_eq!(default_params().len(), PARAM_NAMES.len());
+    }
+
+    #[test]
+    fn runs_and_logs_trajectory() {
+        let params: Vec<Param> = default_params().into_iter().filter(|p| p.name.starts_with("lmr")).collect();
+        let options = Options {
+            engine: Engine::AlphaBeta { nodes: 100 },
+            iterations: 3,
+            pairs: 2,
+            threads: 2,
+            seed: 4,
+            ..Options::default()
+        };
+        let mut spsa = Spsa::new(params, EngineConfig::default(), Vec::new(), options).unwrap();
+        let mut csv = Vec::new();
+        spsa.run(&mut csv, |_, result| assert!(result.abs() <= 4.0)).unwrap();
+        assert_eq!(spsa.iteration(), 3);
+        for param in spsa.params() {
+            assert!((param.min..=param.max).contains(&param.value));
+        }
+
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "iteration,result,lmr_min_depth,lmr_min_moves,lmr_reduction");
+        assert_eq!(lines.len(), 4);
+        assert!(lines[3].starts_with("3,"));
+    }
+
+    #[test]
+    fn falls_back_when_random_openings_end_the_game() {
+        let options = Options { engine: Engine::AlphaBeta { nodes: 50 }, random_plies: 1000, ..Options::default() };
+        let mut spsa = Spsa::new(default_params(), EngineConfig::default(), Vec::new(), options).unwrap();
+        assert!(spsa.step().abs() <= 2.0);
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+/// The two-sided 95% quantile of the standard normal distribution.
+const Z_95: f64 = 1.959_963_984_540_054;
+
+/// The scores of a loss, a draw and a win.
+const WDL_SCORES: [f64; 3] = [0.0, 0.5, 1.0];
+/// The scores of the five kinds of game pair, normalised to between 0 and 1.
+const PENTANOMIAL_SCORES: [f64; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+
+/// The expected score of a player `elo` points stronger than their opponent.
+pub fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// The Elo difference implied by an expected score, infinite for a score of 0 or 1.
+pub fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// The error function, to within about 1.5e-7 (Abramowitz and Stegun 7.1.26).
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let poly =
+        t * (0.254_829_592 + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let y = 1.0 - poly * (-x * x).exp();
+    if x < 0.0 {
+        -y
+    } else {
+        y
+    }
+}
+
+/// The number of samples and the mean and variance of a score, given how many samples had
+/// each score.
+fn moments(counts: &[f64], scores: &[f64]) -> (f64, f64, f64) {
+    let n: f64 = counts.iter().sum();
+    let mean = counts.iter().zip(scores).map(|(c, s)| c * s).sum::<f64>() / n;
+    let variance = counts.iter().zip(scores).map(|(c, s)| c * (s - mean).powi(2)).sum::<f64>() / n;
+    (n, mean, variance)
+}
+
+/// Replaces counts of zero by a tiny one, so that a test isn't stuck at a variance of zero
+/// when, say, every game so far has been won.
+fn regularize<const N: usize>(counts: [f64; N]) -> [f64; N] {
+    counts.map(|c| if c == 0.0 { 1e-3 } else { c })
+}
+
+/// An Elo difference with a 95% confidence interval.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Elo {
+    pub elo: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Elo {
+    /// Estimates the Elo difference from the number, mean and variance of independent samples
+    /// of a score between 0 and 1.
+    ///
+    /// A bound of the interval whose score reaches 0 or 1, as with small or one-sided samples,
+    /// is infinite, as is the estimate itself for a score of 0 or 1.
+    fn from_moments((n, mean, variance): (f64, f64, f64)) -> Self {
+        let error = (variance / n).sqrt() * Z_95;
+        Self {
+            elo: elo_from_score(mean),
+            lower: elo_from_score((mean - error).clamp(0.0, 1.0)),
+            upper: elo_from_score((mean + error).clamp(0.0, 1.0)),
+        }
+    }
+
+    /// Half the width of the confidence interval, as usually quoted after a `±`. Infinite if
+    /// either bound is.
+    pub fn error(&self) -> f64 {
+        if self.lower.is_infinite() || self.upper.is_infinite() {
+            return f64::INFINITY;
+        }
+        (self.upper - self.lower) / 2.0
+    }
+}
+
+impl Display for Elo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2} +/- {:.2}", self.elo, self.error())
+    }
+}
+
+/// Game results from one player's perspective.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Wdl {
+    pub wins: u64,
+    pub draws: u64,
+    pub losses: u64,
+}
+
+impl Wdl {
+    pub fn new(wins: u64, draws: u64, losses: u64) -> Self {
+        Self { wins, draws, losses }
+    }
+
+    pub fn games(&self) -> u64 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// The mean score per game, between 0 and 1. `None` without any games.
+    pub fn score(&self) -> Option<f64> {
+        (self.games() > 0).then(|| (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64)
+    }
+
+    fn counts(&self) -> [f64; 3] {
+        [self.losses as f64, self.draws as f64, self.wins as f64]
+    }
+
+    /// The Elo difference, treating every game as independent. `None` without any games.
+    pub fn elo(&self) -> Option<Elo> {
+        (self.games() > 0).then(|| Elo::from_moments(moments(&self.counts(), &WDL_SCORES)))
+    }
+
+    /// The likelihood of superiority: the probability that the player is the stronger one,
+    /// judging by wins and losses alone.
+    pub fn los(&self) -> f64 {
+        let decisive = (self.wins + self.losses) as f64;
+        if decisive == 0.0 {
+            return 0.5;
+        }
+        0.5 * (1.0 + erf((self.wins as f64 - self.losses as f64) / (2.0 * decisive).sqrt()))
+    }
+}
+
+/// Results of game pairs, counted by the pair's total score for one player: index 0 for
+/// two losses up to index 4 for two wins.
+///
+/// Counting pairs, which share an opening, accounts for the correlation between their games
+/// and gives tighter, more honest error bars than counting the games alone.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Pentanomial(pub [u64; 5]);
+
+impl Pentanomial {
+    pub fn pairs(&self) -> u64 {
+        self.0.iter().sum()
+    }
+
+    /// Records a pair from the scores of its two games (0, 0.5 or 1 each).
+    pub fn add_pair(&mut self, first: f64, second: f64) {
+        self.0[((first + second) * 2.0).round().clamp(0.0, 4.0) as usize] += 1;
+    }
+
+    /// The results of the individual games that are consistent with these pairs. A pair that
+    /// scored one point may have been a win and a loss or two draws, and is counted as two
+    /// draws.
+    pub fn wdl(&self) -> Wdl {
+        let [ll, ld, dd, wd, ww] = self.0;
+        Wdl::new(2 * ww + wd, ld + 2 * dd + wd, 2 * ll + ld)
+    }
+
+    fn counts(&self) -> [f64; 5] {
+        self.0.map(|c| c as f64)
+    }
+
+    /// The Elo difference, treating every pair as independent. `None` without any pairs.
+    pub fn elo(&self) -> Option<Elo> {
+        (self.pairs() > 0).then(|| Elo::from_moments(moments(&self.counts(), &PENTANOMIAL_SCORES)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SprtDecision {
+    /// The Elo difference is at most `elo0`.
+    AcceptH0,
+    /// The Elo difference is at least `elo1`.
+    AcceptH1,
+}
+
+/// A generalised sequential probability ratio test between the hypotheses that the Elo
+/// difference is `elo0` and that it is `elo1`, in logistic Elo.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// The probability of accepting H1 when H0 is true.
+    pub alpha: f64,
+    /// The probability of accepting H0 when H1 is true.
+    pub beta: f64,
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64, alpha: f64, beta: f64) -> Self {
+        Self { elo0, elo1, alpha, beta }
+    }
+
+    /// The log-likelihood ratios below which H0 is accepted and above which H1 is.
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    /// The approximate log-likelihood ratio of samples of a score with the given number,
+    /// mean and variance.
+    fn llr(&self, (n, mean, variance): (f64, f64, f64)) -> f64 {
+        let (s0, s1) = (score_from_elo(self.elo0), score_from_elo(self.elo1));
+        n * (s1 - s0) * (2.0 * mean - s0 - s1) / (2.0 * variance)
+    }
+
+    pub fn llr_wdl(&self, wdl: &Wdl) -> f64 {
+        if wdl.games() == 0 {
+            return 0.0;
+        }
+        self.llr(moments(&regularize(wdl.counts()), &WDL_SCORES))
+    }
+
+    pub fn llr_pentanomial(&self, pentanomial: &Pentanomial) -> f64 {
+        if pentanomial.pairs() == 0 {
+            return 0.0;
+        }
+        self.llr(moments(&regularize(pentanomial.counts()), &PENTANOMIAL_SCORES))
+    }
+
+    /// The outcome of the test at a given log-likelihood ratio, or `None` if it must go on.
+    pub fn decide(&self, llr: f64) -> Option<SprtDecision> {
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            Some(SprtDecision::AcceptH0)
+        } else if llr >= upper {
+            Some(SprtDecision::AcceptH1)
+        } else {
+            None
+        }
+    }
+}
+
+/// Parses `elo0,elo1` or `elo0,elo1,alpha,beta`, with alpha and beta defaulting to 0.05.
+impl FromStr for Sprt {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| "invalid number in SPRT parameters")?;
+        let sprt = match *values.as_slice() {
+            [elo0, elo1] => Self::new(elo0, elo1, 0.05, 0.05),
+            [elo0, elo1, alpha, beta] => Self::new(elo0, elo1, alpha, beta),
+            _ => return Err("expected elo0,elo1 or elo0,elo1,alpha,beta"),
+        };
+        if sprt.elo0 >= sprt.elo1 {
+            return Err("elo0 must be less than elo1");
+        }
+        if !(sprt.alpha > 0.0 && sprt.alpha < 0.5 && sprt.beta > 0.0 && sprt.beta < 0.5) {
+            return Err("alpha and beta must be between 0 and 0.5");
+        }
+        Ok(sprt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{elo_from_score, score_from_elo, Pentanomial, Sprt, SprtDecision, Wdl};
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance
+    }
+
+    #[test]
+    fn elo_from_wdl() {
+        assert!(close(elo_from_score(0.75), 190.8485, 1e-4));
+        assert!(close(score_from_elo(elo_from_score(0.625)), 0.625, 1e-12));
+
+        let wdl = Wdl::new(180, 220, 140);
+        let elo = wdl.elo().unwrap();
+        assert!(close(elo.elo, 25.7832, 1e-4));
+        assert!(close(elo.lower, 3.2826, 1e-4));
+        assert!(close(elo.upper, 48.5021, 1e-4));
+        assert!(close(elo.error(), 22.6097, 1e-4));
+        assert!(close(wdl.los(), 0.987326, 1e-6));
+        assert!(close(Wdl::new(10, 5, 10).los(), 0.5, 1e-6));
+        assert_eq!(Wdl::default().elo(), None);
+        assert_eq!(Wdl::default().score(), None);
+        assert_eq!(Wdl::new(3, 0, 1).score(), Some(0.75));
+    }
+
+    #[test]
+    fn elo_of_lopsided_results() {
+        // the interval of so small a sample reaches a score of 1.
+        let elo = Wdl::new(3, 0, 1).elo().unwrap();
+        assert!(close(elo.elo, 190.8485, 1e-4));
+        assert!(elo.lower.is_finite() && elo.upper == f64::INFINITY);
+        assert_eq!(elo.error(), f64::INFINITY);
+        assert_eq!(elo.to_string(), "190.85 +/- inf");
+
+        let elo = Wdl::new(0, 1, 5).elo().unwrap();
+        assert!(elo.elo.is_finite() && elo.lower == f64::NEG_INFINITY && elo.upper.is_finite());
+
+        for (perfect, sign) in [(Wdl::new(4, 0, 0), 1.0), (Wdl::new(0, 0, 4), -1.0)] {
+            let elo = perfect.elo().unwrap();
+            let infinite = sign * f64::INFINITY;
+            assert_eq!((elo.elo, elo.lower, elo.upper), (infinite, infinite, infinite));
+            assert_eq!(elo.error(), f64::INFINITY);
+        }
+        let elo = Pentanomial([0, 0, 0, 0, 3]).elo().unwrap();
+        assert!(!elo.elo.is_nan() && !elo.error().is_nan());
+    }
+
+    #[test]
+    fn elo_from_pentanomial() {
+        let pentanomial = Pentanomial([20, 120, 300, 140, 25]);
+        let elo = pentanomial.elo().unwrap();
+        assert!(close(elo.elo, 8.6159, 1e-4));
+        assert!(close(elo.lower, -3.1709, 1e-4));
+        assert!(close(elo.upper, 20.4225, 1e-4));
+        assert_eq!(pentanomial.wdl(), Wdl::new(2 * 25 + 140, 120 + 2 * 300 + 140, 2 * 20 + 120));
+        assert_eq!(pentanomial.wdl().games(), 2 * pentanomial.pairs());
+
+        let mut pairs = Pentanomial::default();
+        pairs.add_pair(1.0, 0.5);
+        pairs.add_pair(0.0, 1.0);
+        assert_eq!(pairs, Pentanomial([0, 0, 1, 1, 0]));
+    }
+
+    #[test]
+    fn sprt_llr_and_bounds() {
+        let sprt: Sprt = "0,5".parse().unwrap();
+        let (lower, upper) = sprt.bounds();
+        assert!(close(lower, -2.944439, 1e-6) && close(upper, 2.944439, 1e-6));
+
+        assert!(close(sprt.llr_wdl(&Wdl::new(180, 220, 140)), 0.885183, 1e-6));
+        assert!(close(sprt.llr_pentanomial(&Pentanomial([20, 120, 300, 140, 25])), 0.845523, 1e-6));
+        assert_eq!(sprt.decide(0.9), None);
+        // a perfect score has no variance, which mustn't stop the test from deciding.
+        assert_eq!(sprt.decide(sprt.llr_pentanomial(&Pentanomial([0, 0, 0, 0, 20]))), Some(SprtDecision::AcceptH1));
+        assert_eq!(sprt.decide(3.0), Some(SprtDecision::AcceptH1));
+        assert_eq!(sprt.decide(-3.0), Some(SprtDecision::AcceptH0));
+
+        assert!("5,0".parse::<Sprt>().is_err());
+        assert_eq!("-1,4,0.1,0.2".parse::<Sprt>(), Ok(Sprt::new(-1.0, 4.0, 0.1, 0.2)));
+    }
+}
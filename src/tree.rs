@@ -0,0 +1,244 @@
+use crate::{pgn::Eval, Board, Move};
+
+/// A handle to a node of a [`GameTree`]. Handles stay valid until their node is deleted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/// A position in a game tree, reached by playing `mv` from its parent's position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    /// The move that leads here, or `None` for the root.
+    pub mv: Option<Move>,
+    /// The evaluation of the move, or of the start position at the root.
+    pub eval: Option<Eval>,
+    pub comment: Option<String>,
+    /// Numeric annotation glyphs, such as 1 for a good move and 2 for a mistake.
+    pub nags: Vec<u8>,
+    parent: Option<NodeId>,
+    /// The continuations from here, the main line first.
+    children: Vec<NodeId>,
+}
+
+impl Node {
+    fn new(mv: Option<Move>, parent: Option<NodeId>) -> Self {
+        Self { mv, eval: None, comment: None, nags: Vec::new(), parent, children: Vec::new() }
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+/// A game with variations: a tree of moves from a start position, with a cursor.
+///
+/// Only the start position is stored, and the position at a node is worked out when it is
+/// asked for by replaying the moves that lead to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameTree {
+    start: Board,
+    /// Deleted nodes leave a hole, so that the handles of the others stay valid.
+    nodes: Vec<Option<Node>>,
+    current: NodeId,
+}
+
+impl Default for GameTree {
+    fn default() -> Self {
+        Self::new(Board::default())
+    }
+}
+
+impl GameTree {
+    pub fn new(start: Board) -> Self {
+        Self { start, nodes: vec![Some(Node::new(None, None))], current: NodeId(0) }
+    }
+
+    pub fn start(&self) -> &Board {
+        &self.start
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    /// The node the cursor is on.
+    pub fn current(&self) -> NodeId {
+        self.current
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the node has been deleted.
+    pub fn node(&self, id: NodeId) -> &Node {
+        self.nodes[id.0].as_ref().expect("node was deleted")
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the node has been deleted.
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.nodes[id.0].as_mut().expect("node was deleted")
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.nodes.get(id.0).is_some_and(Option::is_some)
+    }
+
+    /// The moves from the start position to `id`.
+    pub fn moves_to(&self, id: NodeId) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let mut node = self.node(id);
+        while let (Some(mv), Some(parent)) = (node.mv, node.parent) {
+            moves.push(mv);
+            node = self.node(parent);
+        }
+        moves.reverse();
+        moves
+    }
+
+    /// The position at `id`.
+    pub fn board(&self, id: NodeId) -> Board {
+        let mut board = self.start;
+        for mv in self.moves_to(id) {
+            board.make_move(mv);
+        }
+        board
+    }
+
+    /// The nodes of the main line from `id` on, not including `id` itself.
+    pub fn mainline(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.node(id).children.first().copied(), |&id| {
+            self.node(id).children.first().copied()
+        })
+    }
+
+    /// The last node of the main line from `id` on.
+    pub fn mainline_end(&self, id: NodeId) -> NodeId {
+        self.mainline(id).last().unwrap_or(id)
+    }
+
+    /// Adds `mv` as a continuation of `id`, after any existing ones, or returns the node that
+    /// already has it. Returns `None` if the move is illegal there.
+    pub fn add_move(&mut self, id: NodeId, mv: Move) -> Option<NodeId> {
+        if let Some(&child) = self.node(id).children.iter().find(|&&c| self.node(c).mv == Some(mv)) {
+            return Some(child);
+        }
+        if !self.board(id).is_legal(mv) {
+            return None;
+        }
+        let child = NodeId(self.nodes.len());
+        self.nodes.push(Some(Node::new(Some(mv), Some(id))));
+        self.node_mut(id).children.push(child);
+        Some(child)
+    }
+
+    /// Plays `mv` from the current node and moves the cursor to it, adding it as a new
+    /// variation if it has not been played here before.
+    pub fn play(&mut self, mv: Move) -> Option<NodeId> {
+        let child = self.add_move(self.current, mv)?;
+        self.current = child;
+        Some(child)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the node has been deleted.
+    pub fn go_to(&mut self, id: NodeId) {
+        assert!(self.contains(id), "node was deleted");
+        self.current = id;
+    }
+
+    /// Moves the cursor along the main line. Returns false at the end of the line.
+    pub fn forward(&mut self) -> bool {
+        match self.node(self.current).children.first() {
+            Some(&child) => {
+                self.current = child;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the cursor to the parent. Returns false at the root.
+    pub fn back(&mut self) -> bool {
+        match self.node(self.current).parent {
+            Some(parent) => {
+                self.current = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Makes the variation starting at `id` the main line of its parent. Returns false for
+    /// the root.
+    pub fn promote(&mut self, id: NodeId) -> bool {
+        let Some(parent) = self.node(id).parent else { return false };
+        let siblings = &mut self.node_mut(parent).children;
+        let index = siblings.iter().position(|&c| c == id).unwrap();
+        siblings[..=index].rotate_right(1);
+        true
+    }
+
+    /// Deletes `id` and everything after it. The cursor moves to the parent if it was in the
+    /// deleted part. Returns false for the root, which cannot be deleted.
+    pub fn delete(&mut self, id: NodeId) -> bool {
+        let Some(parent) = self.node(id).parent else { return false };
+        self.node_mut(parent).children.retain(|&c| c != id);
+
+        let mut stack = vec![id];
+        while let Some(next) = stack.pop() {
+            let node = self.nodes[next.0].take().unwrap();
+            stack.extend(node.children);
+        }
+        if !self.contains(self.current) {
+            self.current = parent;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GameTree;
+    use crate::{Board, Move};
+
+    fn mv(s: &str) -> Move {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn navigates_and_edits_variations() {
+        let mut tree = GameTree::default();
+        let f1 = tree.play(mv("f1")).unwrap();
+        let b1 = tree.play(mv("b1")).unwrap();
+        assert!(tree.back() && tree.back() && !tree.back());
+        assert_eq!(tree.current(), tree.root());
+
+        let g2 = tree.play(mv("g2")).unwrap();
+        let b2 = tree.play(mv("b2")).unwrap();
+        assert_eq!(tree.play(mv("a1")), None, "a1 is occupied");
+        assert_eq!(tree.node(tree.root()).children(), [f1, g2]);
+        assert_eq!(tree.mainline(tree.root()).collect::<Vec<_>>(), [f1, b1]);
+
+        let mut board = Board::default();
+        board.make_move(mv("g2"));
+        board.make_move(mv("b2"));
+        assert_eq!(tree.board(b2), board);
+        assert_eq!(tree.moves_to(b2), [mv("g2"), mv("b2")]);
+
+        assert!(tree.promote(g2));
+        assert_eq!(tree.mainline_end(tree.root()), b2);
+        tree.go_to(tree.root());
+        assert!(tree.forward() && tree.current() == g2);
+        assert_eq!(tree.play(mv("b2")), Some(b2), "an existing move is reused");
+
+        assert!(tree.delete(g2) && !tree.delete(tree.root()));
+        assert!(!tree.contains(b2) && tree.contains(b1));
+        assert_eq!(tree.current(), tree.root());
+        assert_eq!(tree.mainline(tree.root()).collect::<Vec<_>>(), [f1, b1]);
+    }
+}
@@ -0,0 +1,267 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::{Move, PackedMove};
+
+const ENTRIES_PER_BUCKET: usize = 4;
+const AGE_BITS: u32 = 6;
+const AGE_MASK: u8 = (1 << AGE_BITS) - 1;
+
+/// The kind of bound a stored score represents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    None,
+    /// The true score is at least the stored score (fail-high).
+    Lower,
+    /// The true score is at most the stored score (fail-low).
+    Upper,
+    Exact,
+}
+
+impl Bound {
+    const fn from_bits(bits: u64) -> Self {
+        match bits & 0b11 {
+            0 => Self::None,
+            1 => Self::Lower,
+            2 => Self::Upper,
+            _ => Self::Exact,
+        }
+    }
+
+    const fn bits(self) -> u64 {
+        match self {
+            Self::None => 0,
+            Self::Lower => 1,
+            Self::Upper => 2,
+            Self::Exact => 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub mv: Option<Move>,
+    pub score: i16,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+// An entry is packed into a single u64, so that it can be read and written atomically:
+//
+//   bits  0..16  key verification bits (the low 16 bits of the position key)
+//   bits 16..32  move, as a `PackedMove`
+//   bits 32..48  score
+//   bits 48..56  depth
+//   bits 56..58  bound
+//   bits 58..64  age
+//
+// An all-zero word is an empty slot.
+#[derive(Clone, Copy)]
+struct Packed(u64);
+
+impl Packed {
+    const fn key(self) -> u16 {
+        self.0 as u16
+    }
+
+    const fn mv(self) -> Option<Move> {
+        PackedMove::from_raw((self.0 >> 16) as u16).unpack()
+    }
+
+    const fn score(self) -> i16 {
+        (self.0 >> 32) as u16 as i16
+    }
+
+    const fn depth(self) -> u8 {
+        (self.0 >> 48) as u8
+    }
+
+    const fn bound(self) -> Bound {
+        Bound::from_bits(self.0 >> 56)
+    }
+
+    const fn age(self) -> u8 {
+        (self.0 >> 58) as u8 & AGE_MASK
+    }
+
+    fn new(key: u16, mv: Option<Move>, score: i16, depth: u8, bound: Bound, age: u8) -> Self {
+        let mv = u64::from(mv.map_or(PackedMove::NULL, PackedMove::new).raw());
+        Self(
+            u64::from(key)
+                | mv << 16
+                | u64::from(score as u16) << 32
+                | u64::from(depth) << 48
+                | bound.bits() << 56
+                | u64::from(age & AGE_MASK) << 58,
+        )
+    }
+}
+
+#[repr(align(32))]
+struct Bucket {
+    entries: [AtomicU64; ENTRIES_PER_BUCKET],
+}
+
+impl Bucket {
+    fn new() -> Self {
+        Self { entries: std::array::from_fn(|_| AtomicU64::new(0)) }
+    }
+}
+
+/// A bucketed transposition table that can be shared between search threads without locking.
+///
+/// Every entry lives in one `AtomicU64` together with its key verification bits, so a probe
+/// can never observe a torn write: at worst it sees a different position's entry, which the
+/// verification bits reject with high probability. Callers should still check that a stored
+/// move is legal before playing it.
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    age: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// Creates a table occupying approximately `mb` megabytes.
+    pub fn new(mb: usize) -> Self {
+        let mut tt = Self { buckets: Vec::new(), age: AtomicU8::new(0) };
+        tt.resize(mb);
+        tt
+    }
+
+    /// Resizes the table to approximately `mb` megabytes, clearing it.
+    pub fn resize(&mut self, mb: usize) {
+        let count = (mb * 1024 * 1024 / std::mem::size_of::<Bucket>()).max(1);
+        self.buckets = (0..count).map(|_| Bucket::new()).collect();
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for bucket in &self.buckets {
+            for entry in &bucket.entries {
+                entry.store(0, Ordering::Relaxed);
+            }
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Advances the table's age. Call this once at the start of each search, so that entries
+    /// from earlier searches are preferentially replaced.
+    pub fn new_search(&self) {
+        let age = self.age.load(Ordering::Relaxed);
+        self.age.store((age + 1) & AGE_MASK, Ordering::Relaxed);
+    }
+
+    fn bucket(&self, key: u64) -> &Bucket {
+        // multiply-shift maps the key uniformly onto the buckets without a modulo.
+        let idx = (u128::from(key) * self.buckets.len() as u128) >> 64;
+        &self.buckets[idx as usize]
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let check = key as u16;
+        self.bucket(key).entries.iter().find_map(|slot| {
+            let entry = Packed(slot.load(Ordering::Relaxed));
+            (entry.0 != 0 && entry.key() == check).then(|| Entry {
+                mv: entry.mv(),
+                score: entry.score(),
+                depth: entry.depth(),
+                bound: entry.bound(),
+            })
+        })
+    }
+
+    pub fn store(&self, key: u64, mv: Option<Move>, score: i16, depth: u8, bound: Bound) {
+        let check = key as u16;
+        let age = self.age.load(Ordering::Relaxed);
+        let bucket = self.bucket(key);
+
+        // prefer the slot already holding this position, then an empty slot, then the slot
+        // whose entry is shallowest once its staleness is taken into account.
+        let existing = bucket.entries.iter().find(|slot| {
+            let entry = Packed(slot.load(Ordering::Relaxed));
+            entry.0 != 0 && entry.key() == check
+        });
+        if let Some(slot) = existing {
+            let entry = Packed(slot.load(Ordering::Relaxed));
+            // keep a deeper entry for the same position from this search, unless this one is exact.
+            if bound != Bound::Exact && entry.age() == age && u32::from(depth) + 2 < u32::from(entry.depth()) {
+                return;
+            }
+            // don't lose a known best move to a store that doesn't have one.
+            let mv = mv.or_else(|| entry.mv());
+            slot.store(Packed::new(check, mv, score, depth, bound, age).0, Ordering::Relaxed);
+            return;
+        }
+
+        let target = bucket
+            .entries
+            .iter()
+            .min_by_key(|slot| {
+                let entry = Packed(slot.load(Ordering::Relaxed));
+                if entry.0 == 0 {
+                    return i32::MIN;
+                }
+                let relative_age = i32::from(age.wrapping_sub(entry.age()) & AGE_MASK);
+                i32::from(entry.depth()) - 8 * relative_age
+            })
+            .unwrap_or(&bucket.entries[0]);
+        target.store(Packed::new(check, mv, score, depth, bound, age).0, Ordering::Relaxed);
+    }
+
+    /// An estimate of how full the table is, in permille, counting only entries from the current search.
+    pub fn hashfull(&self) -> usize {
+        let age = self.age.load(Ordering::Relaxed);
+        let sample = self.buckets.len().min(1000 / ENTRIES_PER_BUCKET);
+        let filled = self.buckets[..sample]
+            .iter()
+            .flat_map(|bucket| &bucket.entries)
+            .map(|slot| Packed(slot.load(Ordering::Relaxed)))
+            .filter(|entry| entry.0 != 0 && entry.age() == age)
+            .count();
+        filled * 1000 / (sample * ENTRIES_PER_BUCKET)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bound, TranspositionTable};
+    use crate::{Board, Move, Square};
+
+    #[test]
+    fn store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        let key = Board::default().key();
+        let mv = Move::Double { from: Square::A1, to: Square::C3 };
+        assert_eq!(tt.probe(key), None);
+        tt.store(key, Some(mv), -123, 7, Bound::Lower);
+        let entry = tt.probe(key).unwrap();
+        assert_eq!(entry.mv, Some(mv));
+        assert_eq!(entry.score, -123);
+        assert_eq!(entry.depth, 7);
+        assert_eq!(entry.bound, Bound::Lower);
+    }
+
+    #[test]
+    fn deeper_entries_survive_shallow_stores() {
+        let tt = TranspositionTable::new(1);
+        let key = Board::default().key();
+        tt.store(key, Some(Move::Single { to: Square::B2 }), 50, 10, Bound::Lower);
+        tt.store(key, None, 10, 2, Bound::Upper);
+        assert_eq!(tt.probe(key).unwrap().depth, 10);
+        tt.new_search();
+        tt.store(key, None, 10, 2, Bound::Upper);
+        let entry = tt.probe(key).unwrap();
+        assert_eq!(entry.depth, 2);
+        assert_eq!(entry.mv, Some(Move::Single { to: Square::B2 }));
+    }
+
+    #[test]
+    fn hashfull_tracks_current_age() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+        for i in 0..100_000u64 {
+            tt.store(i.wrapping_mul(0x9E37_79B9_7F4A_7C15), None, 0, 1, Bound::Exact);
+        }
+        assert!(tt.hashfull() > 500);
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+}
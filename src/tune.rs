@@ -0,0 +1,220 @@
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+};
+
+use crate::{
+    eval::{Evaluator, NUM_WEIGHTS, WEIGHT_NAMES},
+    record::{DatasetReader, Format},
+    Board, Player,
+};
+
+/// A labelled training position, reduced to what the tuner needs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TuningPosition {
+    /// The evaluation features from white's perspective.
+    features: [i8; NUM_WEIGHTS],
+    /// The game result for white: 1 for a win, 0.5 for a draw, 0 for a loss.
+    result: f64,
+}
+
+impl TuningPosition {
+    pub fn new(board: &Board, winner: Option<Player>) -> Self {
+        let sign = if board.turn() == Player::White { 1 } else { -1 };
+        let features = Evaluator::features(board).map(|f| (f * sign) as i8);
+        let result = match winner {
+            Some(Player::White) => 1.0,
+            Some(Player::Black) => 0.0,
+            None => 0.5,
+        };
+        Self { features, result }
+    }
+
+    fn eval(&self, weights: &[f64; NUM_WEIGHTS]) -> f64 {
+        self.features.iter().zip(weights).map(|(&f, w)| f64::from(f) * w).sum()
+    }
+}
+
+/// Loads every position with a known result from a dataset. Positions without one are skipped.
+pub fn load_positions(path: &Path, format: Format) -> io::Result<Vec<TuningPosition>> {
+    let reader = DatasetReader::new(BufReader::new(File::open(path)?), format);
+    let mut positions = Vec::new();
+    for record in reader {
+        let record = record?;
+        if let Some(winner) = record.result {
+            positions.push(TuningPosition::new(&record.board, winner));
+        }
+    }
+    Ok(positions)
+}
+
+fn sigmoid(eval: f64, k: f64) -> f64 {
+    1.0 / (1.0 + (-k * eval / 400.0).exp())
+}
+
+/// Tunes evaluation weights by minimising the squared error between game results and the
+/// sigmoid of the evaluation, in the style of Texel's tuning method.
+pub struct Tuner {
+    positions: Vec<TuningPosition>,
+    threads: usize,
+}
+
+impl Tuner {
+    pub fn new(positions: Vec<TuningPosition>, threads: usize) -> Self {
+        Self { positions, threads: threads.max(1) }
+    }
+
+    pub fn positions(&self) -> &[TuningPosition] {
+        &self.positions
+    }
+
+    /// Runs `f` over the positions split between the worker threads and sums the results.
+    fn map_sum<const N: usize>(&self, f: impl Fn(&TuningPosition) -> [f64; N] + Sync) -> [f64; N] {
+        if self.positions.is_empty() {
+            return [0.0; N];
+        }
+        let chunk_size = self.positions.len().div_ceil(self.threads);
+        std::thread::scope(|s| {
+            let handles: Vec<_> = self
+                .positions
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let f = &f;
+                    s.spawn(move || {
+                        chunk.iter().fold([0.0; N], |mut acc, pos| {
+                            acc.iter_mut().zip(f(pos)).for_each(|(a, x)| *a += x);
+                            acc
+                        })
+                    })
+                })
+                .collect();
+            handles.into_iter().fold([0.0; N], |mut acc, handle| {
+                acc.iter_mut().zip(handle.join().unwrap()).for_each(|(a, x)| *a += x);
+                acc
+            })
+        })
+    }
+
+    /// The mean squared error of the positions' results against `sigmoid(k * eval / 400)`.
+    pub fn error(&self, weights: &[f64; NUM_WEIGHTS], k: f64) -> f64 {
+        let [sum] = self.map_sum(|pos| [(pos.result - sigmoid(pos.eval(weights), k)).powi(2)]);
+        sum / self.positions.len().max(1) as f64
+    }
+
+    /// Finds the scaling constant `k` that minimises the error for `weights`, by golden-section search.
+    pub fn fit_k(&self, weights: &[f64; NUM_WEIGHTS]) -> f64 {
+        let ratio = (5f64.sqrt() - 1.0) / 2.0;
+        let (mut lo, mut hi) = (0.0, 10.0);
+        let mut a = hi - ratio * (hi - lo);
+        let mut b = lo + ratio * (hi - lo);
+        let (mut ea, mut eb) = (self.error(weights, a), self.error(weights, b));
+        while hi - lo > 1e-4 {
+            if ea < eb {
+                hi = b;
+                b = a;
+                eb = ea;
+                a = hi - ratio * (hi - lo);
+                ea = self.error(weights, a);
+            } else {
+                lo = a;
+                a = b;
+                ea = eb;
+                b = lo + ratio * (hi - lo);
+                eb = self.error(weights, b);
+            }
+        }
+        (lo + hi) / 2.0
+    }
+
+    /// The gradient of the error with respect to each weight.
+    pub fn gradient(&self, weights: &[f64; NUM_WEIGHTS], k: f64) -> [f64; NUM_WEIGHTS] {
+        let sums = self.map_sum(|pos| {
+            let s = sigmoid(pos.eval(weights), k);
+            let scale = -2.0 * (pos.result - s) * s * (1.0 - s) * k / 400.0;
+            pos.features.map(|f| scale * f64::from(f))
+        });
+        let n = self.positions.len().max(1) as f64;
+        sums.map(|g| g / n)
+    }
+
+    /// Runs `epochs` steps of Adam gradient descent from `weights` with the given learning
+    /// rate, calling `progress` with the epoch, error and weights after each step.
+    pub fn tune(
+        &self,
+        mut weights: [f64; NUM_WEIGHTS],
+        k: f64,
+        epochs: usize,
+        learning_rate: f64,
+        mut progress: impl FnMut(usize, f64, &[f64; NUM_WEIGHTS]),
+    ) -> [f64; NUM_WEIGHTS] {
+        const BETA1: f64 = 0.9;
+        const BETA2: f64 = 0.999;
+        const EPSILON: f64 = 1e-8;
+        let mut m = [0.0; NUM_WEIGHTS];
+        let mut v = [0.0; NUM_WEIGHTS];
+        for epoch in 1..=epochs {
+            let gradient = self.gradient(&weights, k);
+            let bias1 = 1.0 - BETA1.powi(epoch as i32);
+            let bias2 = 1.0 - BETA2.powi(epoch as i32);
+            for i in 0..NUM_WEIGHTS {
+                m[i] = BETA1 * m[i] + (1.0 - BETA1) * gradient[i];
+                v[i] = BETA2 * v[i] + (1.0 - BETA2) * gradient[i] * gradient[i];
+                weights[i] -= learning_rate * (m[i] / bias1) / ((v[i] / bias2).sqrt() + EPSILON);
+            }
+            progress(epoch, self.error(&weights, k), &weights);
+        }
+        weights
+    }
+}
+
+/// Formats tuned weights as Rust source for `eval::DEFAULT_WEIGHTS`.
+pub fn weights_to_rust(weights: &[f64; NUM_WEIGHTS]) -> String {
+    let mut out = String::from("pub const DEFAULT_WEIGHTS: [i32; NUM_WEIGHTS] = [\n");
+    for (weight, name) in weights.iter().zip(WEIGHT_NAMES) {
+        writeln!(out, "    {}, // {name}", weight.round() as i32).unwrap();
+    }
+    out.push_str("];\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{weights_to_rust, Tuner, TuningPosition};
+    use crate::{eval::DEFAULT_WEIGHTS, rng::Rng, Board};
+
+    fn random_game_positions() -> Vec<TuningPosition> {
+        let mut rng = Rng::new(11);
+        let mut positions = Vec::new();
+        for _ in 0..40 {
+            let mut board = Board::default();
+            let mut boards = Vec::new();
+            while !board.game_over() {
+                boards.push(board);
+                board.make_random_move(|lo, hi| rng.range(lo, hi));
+            }
+            let winner = board.outcome().unwrap();
+            positions.extend(boards.iter().map(|b| TuningPosition::new(b, winner)));
+        }
+        positions
+    }
+
+    #[test]
+    fn tuning_reduces_error() {
+        let tuner = Tuner::new(random_game_positions(), 3);
+        let weights = DEFAULT_WEIGHTS.map(f64::from);
+        let k = tuner.fit_k(&weights);
+        let error = tuner.error(&weights, k);
+        assert!(error < tuner.error(&weights, k * 0.8) && error < tuner.error(&weights, k * 1.25));
+
+        let mut last = error;
+        let tuned = tuner.tune(weights, k, 20, 1.0, |_, e, _| last = e);
+        assert!(last < error);
+        assert_eq!(tuner.error(&tuned, k), last);
+
+        let source = weights_to_rust(&tuned);
+        assert!(source.starts_with("pub const DEFAULT_WEIGHTS"));
+        assert_eq!(source.lines().count(), DEFAULT_WEIGHTS.len() + 2);
+    }
+}
@@ -0,0 +1,317 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    str::FromStr,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+use crate::{Board, Move};
+
+#[derive(Debug)]
+pub enum EngineError {
+    Io(io::Error),
+    /// The engine did not answer in time.
+    Timeout,
+    /// The engine closed its output, which usually means it exited.
+    Disconnected,
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Io(e) => write!(f, "{e}"),
+            EngineError::Timeout => write!(f, "engine timed out"),
+            EngineError::Disconnected => write!(f, "engine disconnected"),
+        }
+    }
+}
+
+impl From<io::Error> for EngineError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::BrokenPipe {
+            EngineError::Disconnected
+        } else {
+            EngineError::Io(e)
+        }
+    }
+}
+
+/// An engine's evaluation from an `info` line, from the side to move's perspective.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Score {
+    Cp(i32),
+    /// Moves until a forced win, negative if the engine is being won against.
+    Mate(i32),
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Score::Cp(cp) => write!(f, "{:+.2}", f64::from(cp) / 100.0),
+            Score::Mate(n) if n < 0 => write!(f, "-M{}", -n),
+            Score::Mate(n) => write!(f, "+M{n}"),
+        }
+    }
+}
+
+/// Parses the form written by `Display`: pieces such as `+1.25`, or `+M3` for mates.
+impl FromStr for Score {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, rest) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let sign = if negative { -1 } else { 1 };
+        if let Some(moves) = rest.strip_prefix('M') {
+            return moves.parse::<i32>().map(|n| Score::Mate(sign * n)).map_err(|_| "invalid mate score");
+        }
+        if !rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err("invalid score");
+        }
+        let pieces: f64 = rest.parse().map_err(|_| "invalid score")?;
+        Ok(Score::Cp(sign * (pieces * 100.0).round() as i32))
+    }
+}
+
+/// The arguments of a `go` command. Unset fields are left out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GoParams {
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movetime: Option<Duration>,
+    pub nodes: Option<u64>,
+    pub depth: Option<u8>,
+}
+
+impl Display for GoParams {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "go")?;
+        for (name, time) in [
+            ("wtime", self.wtime),
+            ("btime", self.btime),
+            ("winc", self.winc),
+            ("binc", self.binc),
+            ("movetime", self.movetime),
+        ] {
+            if let Some(time) = time {
+                write!(f, " {name} {}", time.as_millis())?;
+            }
+        }
+        if let Some(nodes) = self.nodes {
+            write!(f, " nodes {nodes}")?;
+        }
+        if let Some(depth) = self.depth {
+            write!(f, " depth {depth}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The answer to a `go` command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BestMove {
+    /// The move exactly as the engine sent it, which may not be a legal move or even parse.
+    pub mv: String,
+    /// The score and depth of the last `info` line that had them.
+    pub score: Option<Score>,
+    pub depth: Option<u32>,
+}
+
+/// An engine running as a child process, spoken to over the UAI protocol.
+pub struct EngineProcess {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    name: String,
+}
+
+impl EngineProcess {
+    /// Starts `program` with `args`. The engine is not spoken to until [`EngineProcess::init`].
+    pub fn spawn(program: &str, args: &[String]) -> io::Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        // a blocking reader thread turns the output into a channel, so that reads can time out.
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self { child, stdin, lines, name: program.to_owned() })
+    }
+
+    /// The name the engine gave in `id name`, or its program name if it gave none.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn send(&mut self, command: &str) -> Result<(), EngineError> {
+        writeln!(self.stdin, "{command}")?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    /// Reads the next line of output, waiting until `deadline` at most.
+    pub fn read_line(&mut self, deadline: Instant) -> Result<String, EngineError> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.lines.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => EngineError::Timeout,
+            RecvTimeoutError::Disconnected => EngineError::Disconnected,
+        })
+    }
+
+    /// Reads lines until one starts with `token`, returning it.
+    fn wait_for(&mut self, token: &str, deadline: Instant) -> Result<String, EngineError> {
+        loop {
+            let line = self.read_line(deadline)?;
+            if line.split_whitespace().next() == Some(token) {
+                return Ok(line);
+            }
+        }
+    }
+
+    /// Sends `uai` and waits for `uaiok`, picking up the engine's name on the way.
+    pub fn init(&mut self, timeout: Duration) -> Result<(), EngineError> {
+        let deadline = Instant::now() + timeout;
+        self.send("uai")?;
+        loop {
+            let line = self.read_line(deadline)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                self.name = name.trim().to_owned();
+            } else if line.trim() == "uaiok" {
+                return Ok(());
+            }
+        }
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), EngineError> {
+        self.send(&format!("setoption name {name} value {value}"))
+    }
+
+    /// Sends `isready` and waits for `readyok`.
+    pub fn is_ready(&mut self, timeout: Duration) -> Result<(), EngineError> {
+        self.send("isready")?;
+        self.wait_for("readyok", Instant::now() + timeout).map(|_| ())
+    }
+
+    /// Tells the engine that the next position is from a new game, and waits until it is ready.
+    pub fn new_game(&mut self, timeout: Duration) -> Result<(), EngineError> {
+        self.send("uainewgame")?;
+        self.is_ready(timeout)
+    }
+
+    /// Sends the position reached by playing `moves` from `start`, then `go`, and waits for
+    /// the best move until `timeout` has passed.
+    pub fn go(
+        &mut self,
+        start: &Board,
+        moves: &[Move],
+        params: &GoParams,
+        timeout: Duration,
+    ) -> Result<BestMove, EngineError> {
+        let mut position = format!("position fen {}", start.fen());
+        if !moves.is_empty() {
+            position.push_str(" moves");
+            for mv in moves {
+                position.push_str(&format!(" {mv}"));
+            }
+        }
+        self.send(&position)?;
+        self.send(&params.to_string())?;
+
+        let deadline = Instant::now() + timeout;
+        let mut score = None;
+        let mut depth = None;
+        loop {
+            let line = self.read_line(deadline)?;
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => {
+                    while let Some(token) = tokens.next() {
+                        match token {
+                            "depth" => depth = tokens.next().and_then(|d| d.parse().ok()).or(depth),
+                            "score" => {
+                                score = match (tokens.next(), tokens.next().and_then(|v| v.parse().ok())) {
+                                    (Some("cp"), Some(cp)) => Some(Score::Cp(cp)),
+                                    (Some("mate"), Some(n)) => Some(Score::Mate(n)),
+                                    _ => score,
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Some("bestmove") => {
+                    let mv = tokens.next().unwrap_or("").to_owned();
+                    return Ok(BestMove { mv, score, depth });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Asks the engine to quit, killing it if it hasn't after a short wait.
+    pub fn quit(mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + Duration::from_millis(500);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+impl Drop for EngineProcess {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GoParams, Score};
+    use std::time::Duration;
+
+    #[test]
+    fn formats_go_and_scores() {
+        let params = GoParams {
+            wtime: Some(Duration::from_millis(1500)),
+            btime: Some(Duration::from_secs(2)),
+            winc: Some(Duration::from_millis(100)),
+            binc: Some(Duration::from_millis(100)),
+            ..GoParams::default()
+        };
+        assert_eq!(params.to_string(), "go wtime 1500 btime 2000 winc 100 binc 100");
+        assert_eq!(GoParams { nodes: Some(500), ..GoParams::default() }.to_string(), "go nodes 500");
+        assert_eq!(Score::Cp(-125).to_string(), "-1.25");
+        assert_eq!(Score::Cp(40).to_string(), "+0.40");
+        assert_eq!(Score::Mate(-3).to_string(), "-M3");
+        for score in [Score::Cp(-125), Score::Cp(0), Score::Cp(7), Score::Mate(4), Score::Mate(-1)] {
+            assert_eq!(score.to_string().parse(), Ok(score));
+        }
+        assert!("M".parse::<Score>().is_err() && "+x".parse::<Score>().is_err());
+    }
+}
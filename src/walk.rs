@@ -0,0 +1,147 @@
+use crate::{Board, Move};
+
+/// What a walk does after a [`TreeVisitor`] hook returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    /// Skips the children of the node just entered. Anywhere else it is the same as `Continue`.
+    Prune,
+    /// Ends the whole walk.
+    Stop,
+}
+
+/// Hooks called while walking a game tree with [`walk`]. Each one is given the position, the
+/// move that led to it, or `None` at the root, and its depth, counted in plies from the root.
+///
+/// Every hook does nothing by default.
+pub trait TreeVisitor {
+    /// Called on a node above the depth limit, before its children.
+    fn enter(&mut self, _board: &Board, _mv: Option<Move>, _depth: u8) -> Control {
+        Control::Continue
+    }
+
+    /// Called on a node above the depth limit, after its children, even if it was pruned.
+    fn leave(&mut self, _board: &Board, _mv: Option<Move>, _depth: u8) -> Control {
+        Control::Continue
+    }
+
+    /// Called on a node at the depth limit.
+    fn leaf(&mut self, _board: &Board, _mv: Option<Move>, _depth: u8) -> Control {
+        Control::Continue
+    }
+}
+
+/// Walks every line of `depth` moves from `board` in move generation order, depth first.
+/// Nodes where the game ends before the depth limit have no children, and are entered and
+/// left without reaching a leaf.
+///
+/// Returns `Control::Stop` if a hook stopped the walk, and `Control::Continue` otherwise.
+pub fn walk(board: &Board, depth: u8, visitor: &mut impl TreeVisitor) -> Control {
+    match visit(board, None, 0, depth, visitor) {
+        Control::Stop => Control::Stop,
+        _ => Control::Continue,
+    }
+}
+
+fn visit(board: &Board, mv: Option<Move>, ply: u8, depth: u8, visitor: &mut impl TreeVisitor) -> Control {
+    if ply == depth {
+        return visitor.leaf(board, mv, ply);
+    }
+    match visitor.enter(board, mv, ply) {
+        Control::Stop => return Control::Stop,
+        Control::Prune => {}
+        Control::Continue => {
+            let mut stopped = false;
+            board.generate_moves(|child_mv| {
+                let mut child = *board;
+                child.make_move(child_mv);
+                stopped = visit(&child, Some(child_mv), ply + 1, depth, visitor) == Control::Stop;
+                stopped
+            });
+            if stopped {
+                return Control::Stop;
+            }
+        }
+    }
+    visitor.leave(board, mv, ply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{walk, Control, TreeVisitor};
+    use crate::{perft::perft, Board, Move};
+
+    #[derive(Default)]
+    struct Counter {
+        entered: u64,
+        left: u64,
+        leaves: u64,
+        max_leaves: Option<u64>,
+        prune_doubles: bool,
+    }
+
+    impl TreeVisitor for Counter {
+        fn enter(&mut self, _board: &Board, mv: Option<Move>, _depth: u8) -> Control {
+            self.entered += 1;
+            if self.prune_doubles && matches!(mv, Some(Move::Double { .. })) {
+                Control::Prune
+            } else {
+                Control::Continue
+            }
+        }
+
+        fn leave(&mut self, _board: &Board, _mv: Option<Move>, _depth: u8) -> Control {
+            self.left += 1;
+            Control::Continue
+        }
+
+        fn leaf(&mut self, _board: &Board, _mv: Option<Move>, depth: u8) -> Control {
+            assert_eq!(depth, 3);
+            self.leaves += 1;
+            if self.max_leaves == Some(self.leaves) {
+                Control::Stop
+            } else {
+                Control::Continue
+            }
+        }
+    }
+
+    /// The children of `board` reached by singles.
+    fn singles(board: &Board) -> Vec<Board> {
+        let mut children = Vec::new();
+        board.generate_moves(|mv| {
+            if let Move::Single { .. } = mv {
+                let mut child = *board;
+                child.make_move(mv);
+                children.push(child);
+            }
+            false
+        });
+        children
+    }
+
+    #[test]
+    fn walks_every_line() {
+        let board: Board = "x5o/7/2-1-2/7/2-1-2/7/o5x x 0 1".parse().unwrap();
+        let mut counter = Counter::default();
+        assert_eq!(walk(&board, 3, &mut counter), Control::Continue);
+        assert_eq!(counter.leaves, perft(&board, 3));
+        assert_eq!(counter.entered, 1 + perft(&board, 1) + perft(&board, 2));
+        assert_eq!(counter.left, counter.entered);
+    }
+
+    #[test]
+    fn prunes_and_stops() {
+        let board = Board::default();
+        let mut counter = Counter { prune_doubles: true, ..Counter::default() };
+        walk(&board, 3, &mut counter);
+        let expected: u64 = singles(&board).iter().flat_map(singles).map(|b| perft(&b, 1)).sum();
+        assert_eq!(counter.leaves, expected);
+        assert!(counter.leaves < perft(&board, 3));
+
+        let mut counter = Counter { max_leaves: Some(10), ..Counter::default() };
+        assert_eq!(walk(&board, 3, &mut counter), Control::Stop);
+        assert_eq!(counter.leaves, 10);
+        assert!(counter.left < counter.entered);
+    }
+}
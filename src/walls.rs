@@ -0,0 +1,165 @@
+#[cfg(feature = "std")]
+use crate::rng::Rng;
+use crate::{apply_symmetry, expand, Board, Square, BB_ALL};
+
+/// The squares of the starting pieces, which walls never cover.
+pub const START_SQUARES: u64 = Square::A1.as_set() | Square::G1.as_set() | Square::A7.as_set() | Square::G7.as_set();
+
+/// A named wall layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub name: &'static str,
+    pub walls: u64,
+}
+
+impl Layout {
+    pub fn board(&self) -> Board {
+        Board::with_walls(self.walls)
+    }
+}
+
+const fn squares(squares: &[Square]) -> u64 {
+    let mut walls = 0;
+    let mut i = 0;
+    while i < squares.len() {
+        walls |= squares[i].as_set();
+        i += 1;
+    }
+    walls
+}
+
+/// The standard layouts, every one symmetric under all the symmetries of the board.
+pub const LAYOUTS: &[Layout] = &[
+    Layout { name: "none", walls: 0 },
+    Layout { name: "center", walls: squares(&[Square::D4]) },
+    Layout { name: "diamond", walls: squares(&[Square::D3, Square::C4, Square::E4, Square::D5]) },
+    Layout { name: "cross", walls: squares(&[Square::D3, Square::C4, Square::D4, Square::E4, Square::D5]) },
+    Layout { name: "pillars", walls: squares(&[Square::C3, Square::E3, Square::C5, Square::E5]) },
+    Layout { name: "corners", walls: squares(&[Square::B2, Square::F2, Square::B6, Square::F6]) },
+    Layout { name: "bars", walls: squares(&[Square::D2, Square::B4, Square::F4, Square::D6]) },
+    Layout {
+        name: "block",
+        walls: squares(&[
+            Square::C3,
+            Square::D3,
+            Square::E3,
+            Square::C4,
+            Square::D4,
+            Square::E4,
+            Square::C5,
+            Square::D5,
+            Square::E5,
+        ]),
+    },
+    Layout {
+        name: "gates",
+        walls: squares(&[
+            Square::C1,
+            Square::E1,
+            Square::A3,
+            Square::G3,
+            Square::A5,
+            Square::G5,
+            Square::C7,
+            Square::E7,
+        ]),
+    },
+];
+
+/// Looks up a standard layout by name.
+pub fn layout(name: &str) -> Option<&'static Layout> {
+    LAYOUTS.iter().find(|layout| layout.name == name)
+}
+
+/// The union of the images of `walls` under the eight symmetries of the board.
+pub fn symmetrize(walls: u64) -> u64 {
+    (0..8).fold(0, |all, sym| all | apply_symmetry(walls & BB_ALL, sym))
+}
+
+/// Whether the squares without walls are all connected to each other by king steps, so that
+/// no part of the board, and in particular no starting piece, is cut off from the rest.
+pub fn is_connected(walls: u64) -> bool {
+    let open = BB_ALL & !walls;
+    let mut reached = open & open.wrapping_neg();
+    loop {
+        let next = (reached | expand(reached)) & open;
+        if next == reached {
+            return reached == open;
+        }
+        reached = next;
+    }
+}
+
+/// Random walls that are symmetric under every symmetry of the board, with at most
+/// `max_walls` of them, that keep the starting squares free and connected to the rest.
+///
+/// The squares are split into the orbits of the symmetries, and each orbit that still fits
+/// is walled with even odds, in a random order.
+#[cfg(feature = "std")]
+pub fn random_walls(rng: &mut Rng, max_walls: u32) -> u64 {
+    let mut orbits = Vec::new();
+    let mut covered = START_SQUARES;
+    for sq in Square::all() {
+        if covered & sq.as_set() == 0 {
+            let orbit = symmetrize(sq.as_set());
+            covered |= orbit;
+            orbits.push(orbit);
+        }
+    }
+
+    // Fisher-Yates, so that every orbit gets the same chance to fit.
+    for i in (1..orbits.len()).rev() {
+        orbits.swap(i, rng.range(0, i + 1));
+    }
+
+    let mut walls = 0;
+    for orbit in orbits {
+        let next = walls | orbit;
+        if next.count_ones() <= max_walls && rng.next_u64() & 1 == 1 && is_connected(next) {
+            walls = next;
+        }
+    }
+    walls
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::{is_connected, layout, random_walls, symmetrize, LAYOUTS, START_SQUARES};
+    use crate::{rng::Rng, Square};
+
+    #[test]
+    fn standard_layouts_are_symmetric_and_open() {
+        for (i, layout) in LAYOUTS.iter().enumerate() {
+            assert_eq!(symmetrize(layout.walls), layout.walls, "{}", layout.name);
+            assert_eq!(layout.walls & START_SQUARES, 0, "{}", layout.name);
+            assert!(is_connected(layout.walls), "{}", layout.name);
+            assert!(LAYOUTS[..i].iter().all(|other| other.name != layout.name));
+            assert!(!layout.board().game_over());
+        }
+        assert_eq!(layout("center").unwrap().board().fen(), "x5o/7/7/3-3/7/7/o5x x 0 1");
+        assert!(layout("nope").is_none());
+    }
+
+    #[test]
+    fn random_walls_are_symmetric_and_bounded() {
+        let mut rng = Rng::new(5);
+        let mut distinct = Vec::new();
+        for max in [0, 4, 8, 16] {
+            for _ in 0..20 {
+                let walls = random_walls(&mut rng, max);
+                assert!(walls.count_ones() <= max);
+                assert_eq!(symmetrize(walls), walls);
+                assert_eq!(walls & START_SQUARES, 0);
+                assert!(is_connected(walls));
+                if !distinct.contains(&walls) {
+                    distinct.push(walls);
+                }
+            }
+        }
+        assert!(distinct.len() > 10);
+        assert_eq!(random_walls(&mut Rng::new(9), 12), random_walls(&mut Rng::new(9), 12));
+
+        // a ring of walls around a corner cuts it off.
+        assert!(!is_connected(Square::B1.as_set() | Square::A2.as_set() | Square::B2.as_set()));
+    }
+}
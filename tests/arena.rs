@@ -0,0 +1,51 @@
+use std::{fs, process::Command};
+
+#[test]
+fn plays_and_adjudicates_games() {
+    let engine = env!("CARGO_BIN_EXE_engine");
+    let dir = std::env::temp_dir().join(format!("ataxxgen-arena-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let pgn = dir.join("games.pgn");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_arena"))
+        .args(["--engine", engine])
+        .args(["--engine", &format!("{engine} --crash-after 3")])
+        .args(["--engine", &format!("{engine} --illegal-after 2")])
+        .args(["--nodes", "200", "--tc", "30+0.1", "--concurrency", "3", "--rounds", "1"])
+        .arg("--pgn")
+        .arg(&pgn)
+        .output()
+        .unwrap();
+    assert!(status.status.success(), "{}", String::from_utf8_lossy(&status.stderr));
+
+    let games = fs::read_to_string(&pgn).unwrap();
+    assert_eq!(games.matches("[Event ").count(), 6);
+    // every game involving a faulty engine is adjudicated against it, and between the two
+    // faulty engines the illegal move always comes before the crash.
+    assert_eq!(games.matches("[Termination \"abandoned\"]").count(), 2);
+    assert_eq!(games.matches("[Termination \"rules infraction\"]").count(), 4);
+    assert!(games.contains("[White \"ataxxgen #2\"]"));
+
+    let standings = String::from_utf8(status.stdout).unwrap();
+    let first = standings.lines().find(|l| l.starts_with("ataxxgen ")).unwrap();
+    assert_eq!(first.split_whitespace().collect::<Vec<_>>(), ["ataxxgen", "4", "4", "0", "0", "4.0"]);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn sprt_stops_a_lopsided_match_early() {
+    let engine = env!("CARGO_BIN_EXE_engine");
+    let output = Command::new(env!("CARGO_BIN_EXE_arena"))
+        .args(["--engine", engine])
+        .args(["--engine", &format!("{engine} --crash-after 1")])
+        .args(["--nodes", "100", "--rounds", "50", "--concurrency", "2", "--sprt", "0,10"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let log = String::from_utf8(output.stderr).unwrap();
+    assert!(log.contains("sprt: H1 accepted"));
+    let games = log.lines().filter(|l| l.starts_with("finished game")).count();
+    assert!(games < 100, "played {games} games");
+}